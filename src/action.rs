@@ -1,9 +1,242 @@
-use std::collections::HashMap;
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap},
+};
 
 use serde::Deserialize;
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+use crate::{Expr, State};
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
 pub struct Action {
     pub duration: i32,
+    #[serde(default)]
     pub deltas: HashMap<String, i32>,
+    #[serde(default)]
+    pub outcomes: Vec<Outcome>,
+    #[serde(default)]
+    pub conditionals: Vec<Conditional>,
+    #[serde(default)]
+    pub effects: HashMap<String, Expr>, // New values, evaluated against the state the action starts in
+    #[serde(default)]
+    pub cooldown: i32, // Minimum time from the end of one use to the start of the next
+    #[serde(default)]
+    pub max_uses: Option<usize>, // Across the model's whole action history
+    #[serde(default)]
+    pub max_uses_per_plan: Option<usize>,
+    #[serde(default)]
+    pub cost: Option<Expr>, // Evaluated against the state the action starts in; defaults to the duration
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(skip)]
+    pub sequence: Vec<(String, Action)>, // The primitive actions performed by a macro action, in order
+}
+
+/// Selects actions by their tags.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct TagFilter {
+    #[serde(default)]
+    pub include: Vec<String>, // If not empty, actions must have at least one of these tags
+    #[serde(default)]
+    pub exclude: Vec<String>, // Actions must have none of these tags
+}
+
+impl TagFilter {
+    pub fn allows(&self, action: &Action) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|tag| action.tags.contains(tag)))
+            && !self.exclude.iter().any(|tag| action.tags.contains(tag))
+    }
+}
+
+/// One possible result of a stochastic action, applied on top of the action's own deltas.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Outcome {
+    pub probability: f32,
+    #[serde(default)]
+    pub deltas: HashMap<String, i32>,
+}
+
+/// Deltas which depend on a condition evaluated against the state the action starts in.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Conditional {
+    pub when: Expr,
+    #[serde(default)]
+    pub deltas: HashMap<String, i32>,
+    #[serde(default)]
+    pub otherwise: HashMap<String, i32>,
+}
+
+impl Action {
+    /// Combine a sequence of actions into a macro action, which is planned as a single step.
+    pub fn sequence(steps: Vec<(String, Action)>) -> Self {
+        let mut deltas = HashMap::new();
+        let mut tags: Vec<String> = vec![];
+        for (_, action) in &steps {
+            for (key, delta) in &action.deltas {
+                *deltas.entry(key.clone()).or_insert(0) += delta;
+            }
+            for tag in &action.tags {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
+        }
+        Self {
+            duration: steps.iter().map(|(_, action)| action.duration).sum(),
+            deltas,
+            tags,
+            sequence: steps,
+            ..Self::default()
+        }
+    }
+
+    /// Whether this is a macro action made up of a sequence of other actions.
+    pub fn is_macro(&self) -> bool {
+        !self.sequence.is_empty()
+    }
+
+    /// The primitive actions performed by this action, expanding any (nested) macro actions.
+    pub fn primitives(&self, label: &str) -> Vec<(String, Action)> {
+        if !self.is_macro() {
+            return vec![(label.to_string(), self.clone())];
+        }
+        self.sequence
+            .iter()
+            .flat_map(|(label, action)| action.primitives(label))
+            .collect()
+    }
+
+    /// The state variables the action depends on: those its conditions and expressions use, and those it may
+    /// consume (which must not go negative).
+    pub fn reads(&self) -> BTreeSet<&str> {
+        let mut reads: BTreeSet<&str> = self
+            .conditionals
+            .iter()
+            .map(|conditional| &conditional.when)
+            .chain(self.effects.values())
+            .chain(&self.cost)
+            .flat_map(Expr::variables)
+            .collect();
+        reads.extend(
+            self.all_deltas()
+                .filter(|(_, delta)| **delta < 0)
+                .map(|(key, _)| key.as_str()),
+        );
+        for (_, step) in &self.sequence {
+            reads.extend(step.reads());
+        }
+        reads
+    }
+
+    /// The state variables the action may change.
+    pub fn writes(&self) -> BTreeSet<&str> {
+        let mut writes: BTreeSet<&str> = self
+            .all_deltas()
+            .map(|(key, _)| key.as_str())
+            .chain(self.effects.keys().map(String::as_str))
+            .collect();
+        for (_, step) in &self.sequence {
+            writes.extend(step.writes());
+        }
+        writes
+    }
+
+    /// The state variables the action overwrites with its effects, rather than adjusting by deltas.
+    pub fn assigns(&self) -> BTreeSet<&str> {
+        let mut assigns: BTreeSet<&str> = self.effects.keys().map(String::as_str).collect();
+        for (_, step) in &self.sequence {
+            assigns.extend(step.assigns());
+        }
+        assigns
+    }
+
+    // Every delta the action might apply, from its own deltas, outcomes and conditionals
+    fn all_deltas(&self) -> impl Iterator<Item = (&String, &i32)> {
+        self.deltas
+            .iter()
+            .chain(self.outcomes.iter().flat_map(|outcome| &outcome.deltas))
+            .chain(
+                self.conditionals.iter().flat_map(|conditional| {
+                    conditional.deltas.iter().chain(&conditional.otherwise)
+                }),
+            )
+    }
+
+    /// Whether the action has more than one possible outcome.
+    pub fn is_stochastic(&self) -> bool {
+        self.outcomes.len() > 1
+    }
+
+    /// The cost of performing the action from the given state.
+    pub fn cost(&self, state: &State) -> f32 {
+        match &self.cost {
+            Some(cost) => cost.evaluate(state),
+            None => self.duration as f32,
+        }
+    }
+
+    /// The combined deltas of performing the action from the given state with the given outcome.
+    pub fn resolve_deltas(
+        &self,
+        state: &State,
+        outcome: Option<&Outcome>,
+    ) -> Cow<'_, HashMap<String, i32>> {
+        if outcome.is_none() && self.conditionals.is_empty() {
+            return Cow::Borrowed(&self.deltas);
+        }
+
+        let mut deltas = self.deltas.clone();
+        let extra_deltas =
+            outcome
+                .map(|outcome| &outcome.deltas)
+                .into_iter()
+                .chain(self.conditionals.iter().map(|conditional| {
+                    if conditional.when.holds(state) {
+                        &conditional.deltas
+                    } else {
+                        &conditional.otherwise
+                    }
+                }));
+        for extra in extra_deltas {
+            for (key, delta) in extra {
+                *deltas.entry(key.clone()).or_insert(0) += delta;
+            }
+        }
+        Cow::Owned(deltas)
+    }
+
+    /// Whether the action has a cooldown or usage limit which depends on the action history.
+    pub fn is_restricted(&self) -> bool {
+        self.cooldown > 0 || self.max_uses.is_some() || self.max_uses_per_plan.is_some()
+    }
+
+    /// The outcome assumed when planning without considering chance.
+    pub fn most_likely_outcome(&self) -> Option<&Outcome> {
+        self.outcomes.iter().reduce(|best, outcome| {
+            if outcome.probability > best.probability {
+                outcome
+            } else {
+                best
+            }
+        })
+    }
+
+    /// The probability of each outcome, normalised so that they sum to one.
+    pub fn outcome_probabilities(&self) -> Vec<f32> {
+        let total: f32 = self
+            .outcomes
+            .iter()
+            .map(|outcome| outcome.probability)
+            .sum();
+        self.outcomes
+            .iter()
+            .map(|outcome| {
+                if total > 0.0 {
+                    outcome.probability / total
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,163 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{Action, Goal, Model, Plan, Planner, State};
+
+/// Something which happened during an agent's tick.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AgentEvent {
+    /// The labelled action was finished before this tick.
+    ActionCompleted(String),
+    Replanned(ReplanReason),
+    /// A replan was considered, but the current plan was kept as no new plan beat it by the switching cost.
+    PlanKept(ReplanReason),
+    /// The labelled action should now be performed.
+    ActionStarted(String),
+}
+
+/// Why an agent made a new plan.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplanReason {
+    /// There were no steps left in the plan while goals are unmet.
+    NoPlan,
+    /// The observed state differed from the state the last action was expected to produce.
+    Diverged,
+    /// The next step of the plan could not be performed from the observed state.
+    Invalid,
+    /// The agent's goals were changed so that a different goal became the most discontented one.
+    GoalChanged,
+    /// The agent has performed as many steps of the plan as its replan interval.
+    Periodic,
+}
+
+/// Executes plans on behalf of an agent, monitoring the world and replanning only when needed.
+#[derive(Clone, Debug)]
+pub struct Agent {
+    model: Model,
+    planner: Planner,
+    plan: VecDeque<(String, Action)>,
+    current: Option<(String, Action)>,
+    expected: Option<State>, // The state the current action should produce
+    goal_changed: bool,
+    replan_interval: Option<usize>,
+    steps_since_plan: usize,
+}
+
+impl Agent {
+    /// Construct an agent with the model's goals, which plans with the planner's actions.
+    pub fn new(model: Model, planner: Planner) -> Self {
+        Self {
+            model,
+            planner,
+            plan: VecDeque::new(),
+            current: None,
+            expected: None,
+            goal_changed: false,
+            replan_interval: None,
+            steps_since_plan: 0,
+        }
+    }
+
+    /// Replan after this many steps, even if the plan is still valid.
+    pub fn with_replan_interval(mut self, steps: usize) -> Self {
+        self.replan_interval = Some(steps);
+        self
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Replace the agent's goals, replanning on the next tick if a different goal becomes dominant.
+    pub fn set_goals(&mut self, goals: HashMap<String, Goal>) {
+        let dominant_goal = self.model.dominant_goal().map(str::to_string);
+        self.model.goals = goals;
+        self.goal_changed |= self.model.dominant_goal() != dominant_goal.as_deref();
+    }
+
+    /// The steps of the current plan which have yet to be started.
+    pub fn plan(&self) -> impl Iterator<Item = &(String, Action)> {
+        self.plan.iter()
+    }
+
+    /// The action the agent is currently performing.
+    pub fn current_action(&self) -> Option<&(String, Action)> {
+        self.current.as_ref()
+    }
+
+    /// Call whenever the agent is ready for its next action, with the state observed once its last action finished.
+    /// Completes the last action, replans if needed and starts the next step of the plan.
+    pub fn tick(&mut self, observed_state: State) -> Vec<AgentEvent> {
+        let mut events = vec![];
+
+        if let Some((label, action)) = self.current.take() {
+            if let Some(model) = self.model.apply(label.clone(), &action) {
+                self.model = model;
+            }
+            self.steps_since_plan += 1;
+            events.push(AgentEvent::ActionCompleted(label));
+        }
+        let diverged = self
+            .expected
+            .take()
+            .is_some_and(|expected| expected != observed_state);
+        self.model.state = observed_state;
+
+        if let Some(reason) = self.replan_reason(diverged) {
+            self.goal_changed = false;
+            let current = Plan {
+                total_discontentment: 0.0,
+                total_time: 0,
+                total_cost: 0.0,
+                actions: self.plan.drain(..).collect(),
+                outcomes: vec![],
+            };
+            let plan = self.planner.replan(&current, &self.model).actions;
+            let kept = !plan.is_empty() && labels(&plan).eq(labels(&current.actions));
+            self.plan = plan.into();
+            self.steps_since_plan = 0;
+            events.push(if kept {
+                AgentEvent::PlanKept(reason)
+            } else {
+                AgentEvent::Replanned(reason)
+            });
+        }
+
+        if let Some((label, action)) = self.plan.pop_front() {
+            self.expected = self
+                .model
+                .apply(label.clone(), &action)
+                .map(|model| model.state);
+            events.push(AgentEvent::ActionStarted(label.clone()));
+            self.current = Some((label, action));
+        }
+        events
+    }
+
+    // Why the current plan should be reconsidered, if it should be
+    fn replan_reason(&self, diverged: bool) -> Option<ReplanReason> {
+        let next_step_is_valid = self
+            .plan
+            .front()
+            .is_some_and(|(label, action)| self.model.apply(label.clone(), action).is_some());
+        if self.plan.is_empty() {
+            (self.model.calculate_discontentment() > 0.0).then_some(ReplanReason::NoPlan)
+        } else if diverged {
+            Some(ReplanReason::Diverged)
+        } else if !next_step_is_valid {
+            Some(ReplanReason::Invalid)
+        } else if self.goal_changed {
+            Some(ReplanReason::GoalChanged)
+        } else if self
+            .replan_interval
+            .is_some_and(|interval| self.steps_since_plan >= interval)
+        {
+            Some(ReplanReason::Periodic)
+        } else {
+            None
+        }
+    }
+}
+
+fn labels(actions: &[(String, Action)]) -> impl Iterator<Item = &str> {
+    actions.iter().map(|(label, _)| label.as_str())
+}
@@ -1,15 +1,211 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
 
 use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
 
-use crate::{Action, Algorithm, Goal, Solution, State};
+use crate::{
+    Action, ActionSchema, Algorithm, CompositeGoal, Constraint, Event, Goal, MctsSettings,
+    RiskMeasure, Solution, State, TagFilter, Task,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub algorithm: Algorithm,
     pub solution: Solution,
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub risk: RiskMeasure,
     pub max_depth: usize,
+    #[serde(default)]
+    pub cost_weight: f32,
+    #[serde(default)]
+    pub switching_cost: f32,
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    pub mcts: MctsSettings,
     pub state: State,
     pub goals: HashMap<String, Goal>,
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    pub composite_goals: HashMap<String, CompositeGoal>,
     pub actions: HashMap<String, Action>,
+    #[serde(default)]
+    pub schemas: HashMap<String, ActionSchema>,
+    #[serde(default)]
+    pub macros: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub rates: HashMap<String, i32>,
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    pub events: HashMap<String, Event>,
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    pub constraints: Vec<Constraint>,
+    #[serde(default)]
+    pub tag_filter: TagFilter,
+    #[serde(default)]
+    pub tag_costs: HashMap<String, f32>,
+    #[serde(default)]
+    pub tasks: HashMap<String, Task>,
+    #[serde(default)]
+    pub root_tasks: Vec<String>,
+}
+
+impl Config {
+    /// Load a configuration file, merging in any `include`d files and resolving action `templates`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let value = load_value(path.as_ref(), &mut vec![])?;
+        let value = resolve_templates(value)?;
+        Ok(serde_yaml::from_value(value)?)
+    }
+
+    /// All available actions: the concrete `actions`, every grounding of the action `schemas` and the `macros`.
+    pub fn grounded_actions(&self) -> Result<HashMap<String, Action>, ConfigError> {
+        let mut actions = self.actions.clone();
+        for (name, schema) in &self.schemas {
+            actions.extend(schema.ground(name)?);
+        }
+
+        let mut macros = HashMap::new();
+        for (name, steps) in &self.macros {
+            let steps = steps
+                .iter()
+                .map(|step| match actions.get(step) {
+                    Some(action) => Ok((step.clone(), action.clone())),
+                    None => Err(ConfigError::UnknownAction(step.clone())),
+                })
+                .collect::<Result<_, _>>()?;
+            macros.insert(name.clone(), Action::sequence(steps));
+        }
+        actions.extend(macros);
+        Ok(actions)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Yaml(serde_yaml::Error),
+    CyclicInclude(PathBuf),
+    UnknownTemplate(String),
+    CyclicTemplate(String),
+    UnknownAction(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, error) => write!(f, "failed to read {}: {}", path.display(), error),
+            Self::Yaml(error) => write!(f, "failed to parse YAML: {}", error),
+            Self::CyclicInclude(path) => write!(f, "{} includes itself", path.display()),
+            Self::UnknownTemplate(name) => write!(f, "unknown template `{}`", name),
+            Self::CyclicTemplate(name) => write!(f, "template `{}` extends itself", name),
+            Self::UnknownAction(name) => write!(f, "unknown action `{}`", name),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<serde_yaml::Error> for ConfigError {
+    fn from(error: serde_yaml::Error) -> Self {
+        Self::Yaml(error)
+    }
+}
+
+// Read a YAML file, with the contents of its `include`d files (relative to it) merged underneath
+fn load_value(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Value, ConfigError> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|error| ConfigError::Io(path.to_path_buf(), error))?;
+    if stack.contains(&canonical) {
+        return Err(ConfigError::CyclicInclude(path.to_path_buf()));
+    }
+    let text =
+        fs::read_to_string(path).map_err(|error| ConfigError::Io(path.to_path_buf(), error))?;
+    let mut value: Value = serde_yaml::from_str(&text)?;
+
+    let includes = match value
+        .as_mapping_mut()
+        .and_then(|root| root.remove("include"))
+    {
+        Some(Value::Sequence(includes)) => includes,
+        Some(include) => vec![include],
+        None => vec![],
+    };
+
+    stack.push(canonical);
+    let mut merged = Value::Mapping(Mapping::new());
+    for include in includes {
+        let include: String = serde_yaml::from_value(include)?;
+        let include_path = path.parent().unwrap_or(Path::new("")).join(include);
+        merge(&mut merged, load_value(&include_path, stack)?);
+    }
+    stack.pop();
+
+    merge(&mut merged, value);
+    Ok(merged)
+}
+
+// Deep-merge `overlay` into `base`, with values from `overlay` taking precedence
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+// Replace each action and schema which `extends` a template with the template merged with its own fields
+fn resolve_templates(mut value: Value) -> Result<Value, ConfigError> {
+    let Some(root) = value.as_mapping_mut() else {
+        return Ok(value);
+    };
+    let templates = match root.remove("templates") {
+        Some(Value::Mapping(templates)) => templates,
+        _ => Mapping::new(),
+    };
+
+    for section in ["actions", "schemas"] {
+        if let Some(Value::Mapping(entries)) = root.get_mut(section) {
+            for (_, entry) in entries.iter_mut() {
+                *entry = extend(entry, &templates, &mut vec![])?;
+            }
+        }
+    }
+    Ok(value)
+}
+
+fn extend(
+    entry: &Value,
+    templates: &Mapping,
+    stack: &mut Vec<String>,
+) -> Result<Value, ConfigError> {
+    let Some(base_name) = entry.get("extends").and_then(Value::as_str) else {
+        return Ok(entry.clone());
+    };
+    if stack.iter().any(|name| name == base_name) {
+        return Err(ConfigError::CyclicTemplate(base_name.to_string()));
+    }
+    let base = templates
+        .get(base_name)
+        .ok_or_else(|| ConfigError::UnknownTemplate(base_name.to_string()))?;
+
+    stack.push(base_name.to_string());
+    let mut resolved = extend(base, templates, stack)?;
+    stack.pop();
+
+    let mut overrides = entry.clone();
+    if let Some(overrides) = overrides.as_mapping_mut() {
+        overrides.remove("extends");
+    }
+    merge(&mut resolved, overrides);
+    Ok(resolved)
 }
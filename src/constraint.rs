@@ -0,0 +1,63 @@
+use serde::Deserialize;
+
+use crate::Model;
+
+/// A behavioural rule restricting which actions may be chosen, based on the model's action history.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub enum Constraint {
+    /// `then` cannot directly follow `first`.
+    NotFollowedBy { first: String, then: String },
+    /// `after` can only be used once `before` has been used more recently than `after` itself.
+    Precedes { before: String, after: String },
+    /// At most one use of any of these actions per plan.
+    AtMostOne(Vec<String>),
+}
+
+impl Constraint {
+    /// Whether the labelled action may be used next from the given model.
+    pub fn allows(&self, model: &Model, label: &str) -> bool {
+        match self {
+            Self::NotFollowedBy { then, .. } if then != label => true,
+            Self::Precedes { after, .. } if after != label => true,
+            Self::AtMostOne(group) if !group.iter().any(|member| member == label) => true,
+            _ => !self.is_blocking(model),
+        }
+    }
+
+    /// Whether the constraint currently blocks the action(s) it restricts.
+    /// This captures everything about the action history which the constraint depends on.
+    pub fn is_blocking(&self, model: &Model) -> bool {
+        let history = &model.action_history;
+        match self {
+            Self::NotFollowedBy { first, .. } => {
+                history.last().is_some_and(|(last, _)| last == first)
+            }
+            Self::Precedes { before, after } => history
+                .iter()
+                .rev()
+                .map(|(used, _)| used)
+                .find(|used| *used == before || *used == after)
+                .is_none_or(|used| used == after),
+            Self::AtMostOne(group) => history[model.plan_start..]
+                .iter()
+                .any(|(used, _)| group.contains(used)),
+        }
+    }
+
+    // The same constraint over relabelled actions
+    pub(crate) fn relabel(&self, relabel: impl Fn(&str) -> String) -> Self {
+        match self {
+            Self::NotFollowedBy { first, then } => Self::NotFollowedBy {
+                first: relabel(first),
+                then: relabel(then),
+            },
+            Self::Precedes { before, after } => Self::Precedes {
+                before: relabel(before),
+                after: relabel(after),
+            },
+            Self::AtMostOne(group) => {
+                Self::AtMostOne(group.iter().map(|label| relabel(label)).collect())
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// When a scheduled event fires.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum Trigger {
+    /// Fire once, at the given time.
+    At(i32),
+    /// Fire repeatedly, at every multiple of the given period.
+    Every(i32),
+}
+
+/// A change to the world which happens at scheduled times, independently of the agent's actions.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct Event {
+    pub trigger: Trigger,
+    #[serde(default)]
+    pub deltas: HashMap<String, i32>,
+    #[serde(default)]
+    pub sets: HashMap<String, i32>,
+}
+
+impl Event {
+    /// Times at which the event fires after `start`, up to and including `end`.
+    pub fn firings(&self, start: i32, end: i32) -> Vec<i32> {
+        match self.trigger {
+            Trigger::At(time) if start < time && time <= end => vec![time],
+            Trigger::At(_) => vec![],
+            Trigger::Every(period) if period > 0 => {
+                let first = start.div_euclid(period) + 1;
+                let last = end.div_euclid(period);
+                (first..=last).map(|n| n * period).collect()
+            }
+            Trigger::Every(_) => vec![],
+        }
+    }
+
+    /// Whether the event may still fire at some point after the given time.
+    pub fn is_pending(&self, time: i32) -> bool {
+        match self.trigger {
+            Trigger::At(at) => time < at,
+            Trigger::Every(period) => period > 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(trigger: Trigger) -> Event {
+        Event {
+            trigger,
+            deltas: HashMap::new(),
+            sets: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn fires_once_within_the_interval() {
+        let once = event(Trigger::At(5));
+        assert_eq!(once.firings(0, 10), vec![5]);
+        assert_eq!(once.firings(4, 5), vec![5]);
+        assert!(once.firings(5, 10).is_empty());
+        assert!(once.firings(0, 4).is_empty());
+        assert!(once.is_pending(4));
+        assert!(!once.is_pending(5));
+    }
+
+    #[test]
+    fn fires_at_every_multiple_of_the_period() {
+        let periodic = event(Trigger::Every(3));
+        assert_eq!(periodic.firings(0, 10), vec![3, 6, 9]);
+        assert_eq!(periodic.firings(3, 6), vec![6]);
+        assert_eq!(periodic.firings(2, 3), vec![3]);
+        assert!(periodic.firings(4, 5).is_empty());
+        assert_eq!(periodic.firings(-4, 0), vec![-3, 0]);
+        assert!(periodic.is_pending(100));
+    }
+
+    #[test]
+    fn never_fires_without_a_positive_period() {
+        for period in [0, -2] {
+            let never = event(Trigger::Every(period));
+            assert!(never.firings(0, 10).is_empty());
+            assert!(!never.is_pending(0));
+        }
+    }
+}
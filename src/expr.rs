@@ -0,0 +1,447 @@
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+use serde::Deserialize;
+
+use crate::State;
+
+/// An arithmetic expression over state variables, such as `weight / 10` or `min(health + 30, max_health)`.
+///
+/// Supports numbers, variable names (missing variables are zero), `+ - * / %`, comparisons, `&& || !`,
+/// parentheses and the functions `min`, `max`, `abs` and `clamp`.
+/// Comparisons and logical operators evaluate to `1` for true and `0` for false.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "ExprSource")]
+pub enum Expr {
+    Number(f32),
+    Variable(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+    Not,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Function {
+    Min,
+    Max,
+    Abs,
+    Clamp,
+}
+
+impl Expr {
+    pub fn evaluate(&self, state: &State) -> f32 {
+        match self {
+            Self::Number(value) => *value,
+            Self::Variable(name) => *state.get(name).unwrap_or(&0) as f32,
+            Self::Unary(op, operand) => {
+                let value = operand.evaluate(state);
+                match op {
+                    UnaryOp::Negate => -value,
+                    UnaryOp::Not => truth(value == 0.0),
+                }
+            }
+            Self::Binary(op, lhs, rhs) => {
+                let (a, b) = (lhs.evaluate(state), rhs.evaluate(state));
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Subtract => a - b,
+                    BinaryOp::Multiply => a * b,
+                    BinaryOp::Divide if b == 0.0 => 0.0,
+                    BinaryOp::Divide => a / b,
+                    BinaryOp::Remainder if b == 0.0 => 0.0,
+                    BinaryOp::Remainder => a % b,
+                    BinaryOp::Less => truth(a < b),
+                    BinaryOp::LessOrEqual => truth(a <= b),
+                    BinaryOp::Greater => truth(a > b),
+                    BinaryOp::GreaterOrEqual => truth(a >= b),
+                    BinaryOp::Equal => truth(a == b),
+                    BinaryOp::NotEqual => truth(a != b),
+                    BinaryOp::And => truth(a != 0.0 && b != 0.0),
+                    BinaryOp::Or => truth(a != 0.0 || b != 0.0),
+                }
+            }
+            Self::Call(function, arguments) => {
+                let values: Vec<_> = arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(state))
+                    .collect();
+                // Missing arguments are zero, since expressions built in code skip the parser's arity check
+                let argument = |index: usize| values.get(index).copied().unwrap_or(0.0);
+                match function {
+                    Function::Min => values.iter().copied().reduce(f32::min).unwrap_or(0.0),
+                    Function::Max => values.iter().copied().reduce(f32::max).unwrap_or(0.0),
+                    Function::Abs => argument(0).abs(),
+                    Function::Clamp => argument(0).max(argument(1)).min(argument(2)),
+                }
+            }
+        }
+    }
+
+    /// Whether the expression holds (is non-zero) in the given state.
+    pub fn holds(&self, state: &State) -> bool {
+        self.evaluate(state) != 0.0
+    }
+
+    /// The names of the state variables the expression depends on.
+    pub fn variables(&self) -> BTreeSet<&str> {
+        let mut variables = BTreeSet::new();
+        self.collect_variables(&mut variables);
+        variables
+    }
+
+    fn collect_variables<'a>(&'a self, variables: &mut BTreeSet<&'a str>) {
+        match self {
+            Self::Number(_) => {}
+            Self::Variable(name) => {
+                variables.insert(name);
+            }
+            Self::Unary(_, operand) => operand.collect_variables(variables),
+            Self::Binary(_, lhs, rhs) => {
+                lhs.collect_variables(variables);
+                rhs.collect_variables(variables);
+            }
+            Self::Call(_, arguments) => {
+                for argument in arguments {
+                    argument.collect_variables(variables);
+                }
+            }
+        }
+    }
+}
+
+fn truth(value: bool) -> f32 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid expression: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl FromStr for Expr {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(ParseError(format!("unexpected `{}` in `{}`", token, text))),
+        }
+    }
+}
+
+// Expressions may be written in YAML as either plain numbers or strings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExprSource {
+    Number(f32),
+    Text(String),
+}
+
+impl TryFrom<ExprSource> for Expr {
+    type Error = ParseError;
+
+    fn try_from(source: ExprSource) -> Result<Self, Self::Error> {
+        match source {
+            ExprSource::Number(value) => Ok(Self::Number(value)),
+            ExprSource::Text(text) => text.parse(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Identifier(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(value) => write!(f, "{}", value),
+            Self::Identifier(name) => write!(f, "{}", name),
+            Self::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+// Two-character symbols come first so that they take precedence over their one-character prefixes
+const SYMBOLS: [&str; 17] = [
+    "<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "!", "(", ")", ",",
+];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = vec![];
+    let mut rest = text.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_digit() || c == '.' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            let value = rest[..end]
+                .parse()
+                .map_err(|_| ParseError(format!("bad number `{}`", &rest[..end])))?;
+            tokens.push(Token::Number(value));
+            rest = &rest[end..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Identifier(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else {
+            return Err(ParseError(format!("unexpected `{}` in `{}`", c, text)));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+// Recursive descent parser, from lowest to highest precedence
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Symbol(candidate)) if *candidate == symbol) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), ParseError> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(ParseError(format!("expected `{}`", symbol)))
+        }
+    }
+
+    fn binary(
+        &mut self,
+        operators: &[(&str, BinaryOp)],
+        next: fn(&mut Self) -> Result<Expr, ParseError>,
+    ) -> Result<Expr, ParseError> {
+        let mut lhs = next(self)?;
+        'outer: loop {
+            for (symbol, op) in operators {
+                if self.eat(symbol) {
+                    lhs = Expr::Binary(*op, Box::new(lhs), Box::new(next(self)?));
+                    continue 'outer;
+                }
+            }
+            return Ok(lhs);
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&[("||", BinaryOp::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&[("&&", BinaryOp::And)], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        self.binary(
+            &[
+                ("<=", BinaryOp::LessOrEqual),
+                (">=", BinaryOp::GreaterOrEqual),
+                ("==", BinaryOp::Equal),
+                ("!=", BinaryOp::NotEqual),
+                ("<", BinaryOp::Less),
+                (">", BinaryOp::Greater),
+            ],
+            Self::sum,
+        )
+    }
+
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        self.binary(
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Subtract)],
+            Self::product,
+        )
+    }
+
+    fn product(&mut self) -> Result<Expr, ParseError> {
+        self.binary(
+            &[
+                ("*", BinaryOp::Multiply),
+                ("/", BinaryOp::Divide),
+                ("%", BinaryOp::Remainder),
+            ],
+            Self::unary,
+        )
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.eat("-") {
+            Ok(Expr::Unary(UnaryOp::Negate, Box::new(self.unary()?)))
+        } else if self.eat("!") {
+            Ok(Expr::Unary(UnaryOp::Not, Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        match self.tokens.get(self.position).cloned() {
+            Some(Token::Number(value)) => {
+                self.position += 1;
+                Ok(Expr::Number(value))
+            }
+            Some(Token::Identifier(name)) => {
+                self.position += 1;
+                if !self.eat("(") {
+                    return Ok(Expr::Variable(name));
+                }
+                let (function, arity) = match name.as_str() {
+                    "min" => (Function::Min, None),
+                    "max" => (Function::Max, None),
+                    "abs" => (Function::Abs, Some(1)),
+                    "clamp" => (Function::Clamp, Some(3)),
+                    _ => return Err(ParseError(format!("unknown function `{}`", name))),
+                };
+                let mut arguments = vec![self.or()?];
+                while self.eat(",") {
+                    arguments.push(self.or()?);
+                }
+                self.expect(")")?;
+                if arity.is_some_and(|arity| arguments.len() != arity) {
+                    return Err(ParseError(format!(
+                        "wrong number of arguments to `{}`",
+                        name
+                    )));
+                }
+                Ok(Expr::Call(function, arguments))
+            }
+            Some(Token::Symbol("(")) => {
+                self.position += 1;
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(token) => Err(ParseError(format!("unexpected `{}`", token))),
+            None => Err(ParseError("unexpected end of expression".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(text: &str) -> f32 {
+        let state: State = serde_yaml::from_str("health: 40\nmax_health: 100").unwrap();
+        text.parse::<Expr>().unwrap().evaluate(&state)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(evaluate("1 + 2 * 3"), 7.0);
+        assert_eq!(evaluate("(1 + 2) * 3"), 9.0);
+        assert_eq!(evaluate("10 - 4 - 3"), 3.0);
+        assert_eq!(evaluate("7 % 4 * 2"), 6.0);
+        assert_eq!(evaluate("1 + 1 < 3 && 2 > 1 || 0"), 1.0);
+        assert_eq!(evaluate("!0 == 1"), 1.0);
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(evaluate("-2 * 3"), -6.0);
+        assert_eq!(evaluate("4 - -2"), 6.0);
+        assert_eq!(evaluate("--health"), 40.0);
+        assert_eq!(evaluate("-(health - max_health)"), 60.0);
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(evaluate("min(health + 30, max_health)"), 70.0);
+        assert_eq!(evaluate("max(3, 9, 4)"), 9.0);
+        assert_eq!(evaluate("min(5)"), 5.0);
+        assert_eq!(evaluate("abs(health - max_health)"), 60.0);
+        assert_eq!(evaluate("clamp(health * 3, 0, max_health)"), 100.0);
+        assert_eq!(evaluate("clamp(-5, 0, 10)"), 0.0);
+    }
+
+    #[test]
+    fn function_arity() {
+        assert!("abs()".parse::<Expr>().is_err());
+        assert!("abs(1, 2)".parse::<Expr>().is_err());
+        assert!("clamp(1, 2)".parse::<Expr>().is_err());
+        assert!("clamp(1, 2, 3, 4)".parse::<Expr>().is_err());
+        assert!("min()".parse::<Expr>().is_err());
+        assert!("sqrt(4)".parse::<Expr>().is_err());
+    }
+
+    #[test]
+    fn division_by_zero_is_zero() {
+        assert_eq!(evaluate("health / 0"), 0.0);
+        assert_eq!(evaluate("health % (max_health - 100)"), 0.0);
+    }
+
+    #[test]
+    fn missing_variables_are_zero() {
+        assert_eq!(evaluate("armour + 1"), 1.0);
+        assert_eq!(evaluate("!armour"), 1.0);
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        assert!("1.2.3".parse::<Expr>().is_err());
+        assert!("foo(".parse::<Expr>().is_err());
+        assert!("min(1,".parse::<Expr>().is_err());
+        assert!("(1 + 2".parse::<Expr>().is_err());
+        assert!("1 2".parse::<Expr>().is_err());
+        assert!("health $ 2".parse::<Expr>().is_err());
+        assert!("".parse::<Expr>().is_err());
+    }
+}
@@ -1,5 +1,7 @@
 use serde::Deserialize;
 
+use crate::State;
+
 #[allow(clippy::enum_variant_names)]
 #[derive(Clone, Debug, Deserialize)]
 pub enum DiscontentmentKind {
@@ -26,3 +28,64 @@ impl Goal {
         self.weight * delta as f32
     }
 }
+
+/// How the discontentment of the children of an `And` goal is combined.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub enum Combine {
+    #[default]
+    Sum,
+    Max,
+}
+
+/// A tree of goals combined with boolean operators.
+#[derive(Clone, Debug, Deserialize)]
+pub enum CompositeGoal {
+    /// A single goal on the named state variable.
+    Leaf {
+        variable: String,
+        #[serde(flatten)]
+        goal: Goal,
+    },
+    /// Every child goal should be met; discontentment is their sum (or max).
+    And {
+        #[serde(default)]
+        combine: Combine,
+        goals: Vec<CompositeGoal>,
+    },
+    /// Any child goal may be met; discontentment is that of the least discontented child.
+    Or(Vec<CompositeGoal>),
+    /// The child goal should not be met; discontentment is `weight` while it is.
+    Not {
+        weight: f32,
+        goal: Box<CompositeGoal>,
+    },
+}
+
+impl CompositeGoal {
+    pub fn discontentment(&self, state: &State) -> f32 {
+        match self {
+            Self::Leaf { variable, goal } => {
+                goal.discontentment(*state.get(variable).unwrap_or(&0))
+            }
+            Self::And { combine, goals } => {
+                let values = goals.iter().map(|goal| goal.discontentment(state));
+                match combine {
+                    Combine::Sum => values.sum(),
+                    Combine::Max => values.fold(0.0, f32::max),
+                }
+            }
+            Self::Or(goals) => goals
+                .iter()
+                .map(|goal| goal.discontentment(state))
+                .reduce(f32::min)
+                .unwrap_or(0.0),
+            Self::Not { weight, goal } => {
+                if goal.discontentment(state) < f32::EPSILON {
+                    *weight
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
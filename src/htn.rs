@@ -0,0 +1,48 @@
+use serde::Deserialize;
+
+use crate::{Expr, State};
+
+/// A compound task for hierarchical (HTN) planning, performed by one of its ordered methods.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Task {
+    pub methods: Vec<Method>,
+}
+
+/// One way of performing a compound task: a sequence of subtasks, each naming a compound task or an action.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Method {
+    /// The method is only considered when this precondition holds; it always applies if absent.
+    #[serde(default)]
+    pub when: Option<Expr>,
+    pub subtasks: Vec<String>,
+}
+
+impl Task {
+    /// The methods whose preconditions hold in the given state, in order of preference.
+    pub fn applicable_methods<'a>(&'a self, state: &'a State) -> impl Iterator<Item = &'a Method> {
+        self.methods.iter().filter(|method| method.applies(state))
+    }
+
+    // The same task with relabelled subtasks
+    pub(crate) fn relabel(&self, relabel: impl Fn(&str) -> String) -> Self {
+        let methods = self
+            .methods
+            .iter()
+            .map(|method| Method {
+                when: method.when.clone(),
+                subtasks: method
+                    .subtasks
+                    .iter()
+                    .map(|subtask| relabel(subtask))
+                    .collect(),
+            })
+            .collect();
+        Self { methods }
+    }
+}
+
+impl Method {
+    pub fn applies(&self, state: &State) -> bool {
+        self.when.as_ref().is_none_or(|when| when.holds(state))
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::{Action, Constraint, Event, Model, ModelKey, TagFilter};
+
+/// The search graph of `Planner::plan_incremental`, kept between calls in the manner of Lifelong Planning A* (LPA*).
+/// Every model reached keeps its successors and its cost from the start, so that when the start moves to a model
+/// already reached, or the goals or action costs change, only the models whose costs are affected are searched again.
+///
+/// The graph is discarded when the start is a model it hasn't reached, or when it holds more than `capacity` models.
+#[derive(Clone, Debug)]
+pub struct IncrementalSearch {
+    pub(crate) dynamics: Option<Dynamics>,
+    pub(crate) vertices: Vec<Vertex>,
+    pub(crate) index: HashMap<ModelKey, usize>,
+    pub(crate) start: usize,
+    pub(crate) goal: Goal,
+    pub(crate) queue: BinaryHeap<Queued>,
+    capacity: usize,
+}
+
+// Everything which determines the graph of models, besides the start
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Dynamics {
+    pub(crate) actions: HashMap<String, Action>, // Without their costs, which only affect the edge costs
+    pub(crate) constraints: Vec<Constraint>,
+    pub(crate) tag_filter: TagFilter,
+    pub(crate) rates: HashMap<String, i32>,
+    pub(crate) events: HashMap<String, Event>,
+    pub(crate) max_depth: usize,
+}
+
+// A model reached by the search, with its LPA* cost estimates
+#[derive(Clone, Debug)]
+pub(crate) struct Vertex {
+    pub(crate) model: Model,
+    pub(crate) discontentment: f32, // Under the goals of the latest search
+    pub(crate) g: f32,
+    pub(crate) rhs: f32, // The cost through the best predecessor, which `g` is brought up to date with
+    pub(crate) depth: usize, // Steps from the start through the best predecessor
+    pub(crate) edges: Option<Vec<Edge>>, // `None` until the model is expanded
+    pub(crate) predecessors: Vec<(usize, usize)>, // Vertex and index of the edge into this vertex
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Edge {
+    pub(crate) label: String,
+    pub(crate) to: usize,
+    pub(crate) cost: f32, // The discontentment reached plus the weighted cost of the action
+}
+
+// The virtual goal, reached with no further cost from every model without discontentment
+#[derive(Clone, Debug)]
+pub(crate) struct Goal {
+    pub(crate) g: f32,
+    pub(crate) rhs: f32,
+}
+
+// An entry in the priority queue, which is stale if its vertex's key has changed since it was queued
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Queued {
+    pub(crate) key: f32,
+    pub(crate) vertex: Option<usize>, // `None` for the goal
+}
+
+impl Default for IncrementalSearch {
+    fn default() -> Self {
+        Self {
+            dynamics: None,
+            vertices: vec![],
+            index: HashMap::new(),
+            start: 0,
+            goal: Goal {
+                g: f32::INFINITY,
+                rhs: f32::INFINITY,
+            },
+            queue: BinaryHeap::new(),
+            capacity: 10_000,
+        }
+    }
+}
+
+impl IncrementalSearch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of models kept between searches, beyond which the graph is discarded.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// The number of models reached.
+    pub fn len(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.dynamics = None;
+        self.reset();
+    }
+
+    // Discard the graph, keeping the dynamics it was built with
+    pub(crate) fn reset(&mut self) {
+        self.vertices.clear();
+        self.index.clear();
+        self.start = 0;
+        self.goal = Goal {
+            g: f32::INFINITY,
+            rhs: f32::INFINITY,
+        };
+        self.queue.clear();
+    }
+
+    // Discard the graph if it was built with different dynamics, or has grown beyond capacity
+    pub(crate) fn prepare(&mut self, dynamics: Dynamics) {
+        if self.dynamics.as_ref() != Some(&dynamics) {
+            self.dynamics = Some(dynamics);
+            self.reset();
+        } else if self.vertices.len() > self.capacity {
+            self.reset();
+        }
+    }
+}
+
+impl Vertex {
+    pub(crate) fn new(model: Model, discontentment: f32) -> Self {
+        Self {
+            model,
+            discontentment,
+            g: f32::INFINITY,
+            rhs: f32::INFINITY,
+            depth: 0,
+            edges: None,
+            predecessors: vec![],
+        }
+    }
+
+    pub(crate) fn key(&self) -> f32 {
+        self.g.min(self.rhs)
+    }
+}
+
+impl Goal {
+    pub(crate) fn key(&self) -> f32 {
+        self.g.min(self.rhs)
+    }
+}
+
+impl Eq for Queued {}
+
+// Reversed, so that the queue pops the lowest key first
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .key
+            .total_cmp(&self.key)
+            .then_with(|| other.vertex.cmp(&self.vertex))
+    }
+}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -1,13 +1,41 @@
 mod action;
+mod agent;
 mod config;
+mod constraint;
+mod event;
+mod expr;
 mod goal;
+mod htn;
+mod incremental_search;
+mod mcts;
 mod model;
+mod partial_order;
+mod plan_tree;
 mod planner;
+mod reservation;
+mod schedule;
+mod schema;
 mod state;
+mod team;
+mod validation;
 
-pub use action::Action;
-pub use config::Config;
-pub use goal::Goal;
-pub use model::Model;
-pub use planner::{Algorithm, Plan, Planner, Solution};
+pub use action::{Action, Conditional, Outcome, TagFilter};
+pub use agent::{Agent, AgentEvent, ReplanReason};
+pub use config::{Config, ConfigError};
+pub use constraint::Constraint;
+pub use event::{Event, Trigger};
+pub use expr::{BinaryOp, Expr, Function, ParseError, UnaryOp};
+pub use goal::{Combine, CompositeGoal, DiscontentmentKind, Goal};
+pub use htn::{Method, Task};
+pub use incremental_search::IncrementalSearch;
+pub use mcts::{MctsSettings, RolloutPolicy};
+pub use model::{Model, ModelKey};
+pub use partial_order::{Link, LinkKind, PartialOrderPlan};
+pub use plan_tree::{Branch, PlanTree};
+pub use planner::{Algorithm, Plan, PlanOutcome, Planner, RiskMeasure, Solution};
+pub use reservation::{Reservation, ReservationTable};
+pub use schedule::{Schedule, ScheduledAction};
+pub use schema::{ActionSchema, Argument, Parameter};
 pub use state::State;
+pub use team::{SyncPoint, TeamMember, TeamPlan, TeamPlanner};
+pub use validation::{ApplyError, StepFailure, ValidationReport};
@@ -1,4 +1,4 @@
-use std::{cmp::Ordering, fs};
+use std::cmp::Ordering;
 
 use colored::*;
 
@@ -46,17 +46,24 @@ fn print_state_changes(old_state: &State, state: &State) {
 
 fn main() {
     // Read in (or alternatively, build) the configuration
-    let config_str = fs::read_to_string("config.yml").expect("Failed to read config file");
-    let config: Config = serde_yaml::from_str(&config_str).expect("Failed to parse YAML");
+    let config = Config::load("config.yml").expect("Failed to load config");
+
+    let actions = config.grounded_actions().expect("Failed to ground actions");
 
     // Build the model and planner
-    let mut model = Model::new(config.state, config.goals);
-    let planner = Planner::new(
-        config.algorithm,
-        config.solution,
-        config.max_depth,
-        config.actions,
-    );
+    let mut model = Model::new(config.state, config.goals)
+        .with_composite_goals(config.composite_goals)
+        .with_rates(config.rates)
+        .with_events(config.events);
+    let planner = Planner::new(config.algorithm, config.solution, config.max_depth, actions)
+        .with_risk(config.risk)
+        .with_cost_weight(config.cost_weight)
+        .with_switching_cost(config.switching_cost)
+        .with_mcts(config.mcts)
+        .with_constraints(config.constraints)
+        .with_tag_filter(config.tag_filter)
+        .with_tag_costs(config.tag_costs)
+        .with_tasks(config.tasks, config.root_tasks);
 
     // Generate the plan
     let plan = planner.plan(&model);
@@ -68,14 +75,31 @@ fn main() {
         format!("({:.2})", model.calculate_discontentment()).green()
     );
     for (label, action) in plan.actions.iter() {
-        if let Some(next_model) = model.apply(label.to_string(), action) {
-            print_state_changes(&model.state, &next_model.state);
-            print!(
-                "{} ",
-                format!("({:.2})", next_model.calculate_discontentment()).green()
+        match model.try_apply(label.to_string(), action) {
+            Ok(next_model) => {
+                print_state_changes(&model.state, &next_model.state);
+                print!(
+                    "{} ",
+                    format!("({:.2})", next_model.calculate_discontentment()).green()
+                );
+                println!("{}", label);
+                model = next_model;
+            }
+            Err(error) => {
+                println!("{}", format!("cannot {}: {}", label, error).red());
+                break;
+            }
+        }
+    }
+
+    if !plan.outcomes.is_empty() {
+        println!();
+        for outcome in &plan.outcomes {
+            println!(
+                "{:>5.1}% {}",
+                outcome.probability * 100.0,
+                format!("({:.2})", outcome.discontentment).green()
             );
-            println!("{}", label);
-            model = next_model;
         }
     }
 }
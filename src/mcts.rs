@@ -0,0 +1,68 @@
+use serde::Deserialize;
+
+/// How Monte Carlo tree search chooses actions during rollouts, beyond the search tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+pub enum RolloutPolicy {
+    /// A uniformly random action.
+    #[default]
+    Random,
+    /// The action leading to the lowest discontentment (plus weighted cost) after one step.
+    Greedy,
+    /// A random action with the given probability, otherwise the greedy one.
+    EpsilonGreedy(f32),
+}
+
+/// Settings for the `Mcts` algorithm.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct MctsSettings {
+    pub iterations: usize,
+    pub exploration: f32, // The UCT exploration constant
+    pub rollout: RolloutPolicy,
+    pub seed: u64, // Seeds the random number generator, so that plans are reproducible
+}
+
+impl Default for MctsSettings {
+    fn default() -> Self {
+        Self {
+            iterations: 1000,
+            exploration: std::f32::consts::SQRT_2,
+            rollout: RolloutPolicy::default(),
+            seed: 0,
+        }
+    }
+}
+
+// A small pseudo-random number generator (xorshift64*), to avoid depending on an external crate
+#[derive(Clone, Debug)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        // Scramble the seed, keeping the state non-zero as xorshift requires
+        const SCRAMBLE: u64 = 0x9E37_79B9_7F4A_7C15;
+        match seed ^ SCRAMBLE {
+            0 => Self(SCRAMBLE),
+            state => Self(state),
+        }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // A number in [0, 1)
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // An index in [0, n)
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
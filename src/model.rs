@@ -1,13 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use crate::{Action, Goal, State};
+use crate::{Action, ApplyError, CompositeGoal, Event, Goal, Outcome, State};
 
 #[derive(Debug, Clone)]
 pub struct Model {
     pub time: i32,
     pub state: State,
     pub goals: HashMap<String, Goal>,
+    pub composite_goals: HashMap<String, CompositeGoal>,
+    pub rates: HashMap<String, i32>,
+    pub events: HashMap<String, Event>,
     pub action_history: Vec<(String, Action)>,
+    pub plan_start: usize, // Index into the action history at which the current plan begins
 }
 
 impl Model {
@@ -17,32 +21,234 @@ impl Model {
             time: 0,
             state,
             goals,
+            composite_goals: HashMap::new(),
+            rates: HashMap::new(),
+            events: HashMap::new(),
             action_history: vec![],
+            plan_start: 0,
         }
     }
 
+    /// Add composite goals which contribute to the model's discontentment alongside its simple goals.
+    pub fn with_composite_goals(mut self, composite_goals: HashMap<String, CompositeGoal>) -> Self {
+        self.composite_goals = composite_goals;
+        self
+    }
+
+    /// Set the per-tick rates at which state variables change while actions are running.
+    pub fn with_rates(mut self, rates: HashMap<String, i32>) -> Self {
+        self.rates = rates;
+        self
+    }
+
+    /// Set the scheduled events which change the state as time passes.
+    pub fn with_events(mut self, events: HashMap<String, Event>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Mark the start of a new plan, from which per-plan usage limits are counted.
+    pub fn begin_plan(&self) -> Self {
+        let mut model = self.clone();
+        model.plan_start = model.action_history.len();
+        model
+    }
+
+    /// Whether the action's cooldown and usage limits allow it to be used now.
+    pub fn is_available(&self, label: &str, action: &Action) -> bool {
+        if !action.is_restricted() {
+            return true;
+        }
+        if action
+            .max_uses
+            .is_some_and(|max_uses| self.uses(label, 0) >= max_uses)
+        {
+            return false;
+        }
+        if action
+            .max_uses_per_plan
+            .is_some_and(|max_uses| self.uses(label, self.plan_start) >= max_uses)
+        {
+            return false;
+        }
+        self.time_since_use(label)
+            .is_none_or(|elapsed| elapsed >= action.cooldown)
+    }
+
+    // Number of times the labelled action appears in the action history from the given index
+    fn uses(&self, label: &str, from: usize) -> usize {
+        self.action_history
+            .iter()
+            .skip(from)
+            .filter(|(used, _)| used == label)
+            .count()
+    }
+
+    // Time since the labelled action last finished, if it has been used
+    fn time_since_use(&self, label: &str) -> Option<i32> {
+        let mut elapsed = 0;
+        for (used, action) in self.action_history.iter().rev() {
+            if used == label {
+                return Some(elapsed);
+            }
+            elapsed += action.duration;
+        }
+        None
+    }
+
+    /// Apply an action, along with the passive rates and any events over its duration, returning the resulting model if valid.
+    /// Stochastic actions are assumed to have their most likely outcome.
     pub fn apply(&self, label: String, action: &Action) -> Option<Self> {
-        if let Some(next_state) = self.state.apply(action) {
-            let mut updated_action_history = self.action_history.clone();
-            updated_action_history.push((label, action.clone()));
-            Some(Self {
-                time: self.time + action.duration,
-                state: next_state,
-                goals: self.goals.clone(),
-                action_history: updated_action_history,
+        self.try_apply(label, action).ok()
+    }
+
+    /// Apply an action like `apply`, returning why the action cannot be performed if it can't.
+    pub fn try_apply(&self, label: String, action: &Action) -> Result<Self, ApplyError> {
+        self.apply_outcome(label, action, action.most_likely_outcome())
+    }
+
+    /// Apply every possible outcome of an action, returning each resulting model with its probability.
+    /// Returns `None` if any of the outcomes would be invalid.
+    pub fn apply_outcomes(&self, label: String, action: &Action) -> Option<Vec<(f32, Self)>> {
+        if action.outcomes.is_empty() {
+            return self.apply(label, action).map(|model| vec![(1.0, model)]);
+        }
+        action
+            .outcomes
+            .iter()
+            .zip(action.outcome_probabilities())
+            .map(|(outcome, probability)| {
+                self.apply_outcome(label.clone(), action, Some(outcome))
+                    .ok()
+                    .map(|model| (probability, model))
             })
-        } else {
-            None
+            .collect()
+    }
+
+    fn apply_outcome(
+        &self,
+        label: String,
+        action: &Action,
+        outcome: Option<&Outcome>,
+    ) -> Result<Self, ApplyError> {
+        if action.is_macro() {
+            return action
+                .sequence
+                .iter()
+                .try_fold(self.clone(), |model, (label, step)| {
+                    model.try_apply(label.clone(), step)
+                });
+        }
+        if !self.is_available(&label, action) {
+            return Err(ApplyError::Unavailable);
         }
+        let next_state = self.state.try_apply_outcome(action, outcome)?;
+        let mut next_model = self.clone();
+        next_model.time += action.duration;
+        next_model.state = self.pass_time(next_state, self.time, next_model.time)?;
+        next_model.action_history.push((label, action.clone()));
+        Ok(next_model)
+    }
+
+    // Advance the state from `start` to `end`, applying the rates up to each event's firing and the event in turn
+    pub(crate) fn pass_time(
+        &self,
+        mut state: State,
+        start: i32,
+        end: i32,
+    ) -> Result<State, ApplyError> {
+        let mut firings: Vec<_> = self
+            .events
+            .iter()
+            .flat_map(|(name, event)| {
+                event
+                    .firings(start, end)
+                    .into_iter()
+                    .map(move |time| (time, name, event))
+            })
+            .collect();
+        firings.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        let mut now = start;
+        for (time, _, event) in firings {
+            state = state
+                .apply_rates(&self.rates, time - now)?
+                .apply_event(event);
+            now = time;
+        }
+        state.apply_rates(&self.rates, end - now)
+    }
+
+    /// Key identifying this model during search.
+    /// The time is only included while scheduled events can still change the model's future,
+    /// and the action history only through the remaining cooldowns and usage counts of restricted actions.
+    pub fn key(&self) -> ModelKey {
+        let is_time_dependent = self
+            .events
+            .values()
+            .any(|event| event.is_pending(self.time));
+        let mut restrictions = BTreeMap::new();
+        for (label, action) in &self.action_history {
+            if action.is_restricted() && !restrictions.contains_key(label) {
+                let cooldown = self
+                    .time_since_use(label)
+                    .map_or(0, |elapsed| (action.cooldown - elapsed).max(0));
+                let uses = action.max_uses.map_or(0, |_| self.uses(label, 0));
+                let plan_uses = action
+                    .max_uses_per_plan
+                    .map_or(0, |_| self.uses(label, self.plan_start));
+                restrictions.insert(label.clone(), (cooldown, uses, plan_uses));
+            }
+        }
+        ModelKey {
+            state: self.state.clone(),
+            time: is_time_dependent.then_some(self.time),
+            restrictions,
+            constraints: vec![],
+        }
+    }
+
+    /// The name of the (composite) goal contributing the most discontentment, if any is discontented.
+    pub fn dominant_goal(&self) -> Option<&str> {
+        self.goals
+            .iter()
+            .map(|(name, goal)| {
+                let current_value = *self.state.get(name).unwrap_or(&0);
+                (name, goal.discontentment(current_value))
+            })
+            .chain(
+                self.composite_goals
+                    .iter()
+                    .map(|(name, goal)| (name, goal.discontentment(&self.state))),
+            )
+            .filter(|(_, discontentment)| *discontentment > 0.0)
+            .max_by(|(a_name, a), (b_name, b)| a.total_cmp(b).then(b_name.cmp(a_name)))
+            .map(|(name, _)| name.as_str())
     }
 
     pub fn calculate_discontentment(&self) -> f32 {
+        self.discontentment_of(&self.state)
+    }
+
+    // The discontentment this model's goals would have with the given state
+    pub(crate) fn discontentment_of(&self, state: &State) -> f32 {
         let mut total_discontentment = 0.0;
         for (name, goal) in self.goals.iter() {
-            let current_value = *self.state.get(name).unwrap_or(&0);
+            let current_value = *state.get(name).unwrap_or(&0);
             let discontentment = goal.discontentment(current_value);
             total_discontentment += discontentment;
         }
+        for goal in self.composite_goals.values() {
+            total_discontentment += goal.discontentment(state);
+        }
         total_discontentment
     }
 }
+
+/// Identifies the parts of a model which determine its future during search.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ModelKey {
+    state: State,
+    time: Option<i32>,
+    restrictions: BTreeMap<String, (i32, usize, usize)>,
+    pub(crate) constraints: Vec<bool>, // Whether each of the planner's constraints is blocking
+}
@@ -0,0 +1,115 @@
+use std::collections::BTreeSet;
+
+use crate::Action;
+
+/// Why one step of a partial-order plan must be performed before another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkKind {
+    /// The earlier step changes a variable which the later step depends on.
+    Causal,
+    /// The later step changes a variable which the earlier step depends on.
+    Threat,
+    /// Both steps change a variable and at least one overwrites it, so their order decides its final value.
+    Overwrite,
+}
+
+/// An ordering between two steps of a partial-order plan, due to a state variable they share.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Link {
+    pub before: usize,
+    pub after: usize,
+    pub variable: String,
+    pub kind: LinkKind,
+}
+
+/// A plan's steps with only the orderings required by the state variables they read and write,
+/// so that independent steps can be performed concurrently.
+#[derive(Clone, Debug)]
+pub struct PartialOrderPlan {
+    pub steps: Vec<(String, Action)>,
+    pub links: Vec<Link>,
+}
+
+impl PartialOrderPlan {
+    /// Lift a sequence of steps into a partial order, linking every pair of steps which share a variable
+    /// that either of them changes.
+    pub fn new(steps: Vec<(String, Action)>) -> Self {
+        let mut links = vec![];
+        for (after, (_, later)) in steps.iter().enumerate() {
+            for (before, (_, earlier)) in steps[..after].iter().enumerate() {
+                links.extend(
+                    dependencies(earlier, later)
+                        .into_iter()
+                        .map(|(variable, kind)| Link {
+                            before,
+                            after,
+                            variable,
+                            kind,
+                        }),
+                );
+            }
+        }
+        Self { steps, links }
+    }
+
+    /// The steps which must be performed directly before the given step.
+    pub fn predecessors(&self, step: usize) -> BTreeSet<usize> {
+        self.links
+            .iter()
+            .filter(|link| link.after == step)
+            .map(|link| link.before)
+            .collect()
+    }
+
+    /// Whether step `before` must be performed (directly or indirectly) before step `after`.
+    pub fn must_precede(&self, before: usize, after: usize) -> bool {
+        self.predecessors(after)
+            .into_iter()
+            .any(|step| step == before || (step > before && self.must_precede(before, step)))
+    }
+
+    /// Group the steps into stages, each of which only depends on earlier stages,
+    /// so the steps within a stage may be performed concurrently.
+    pub fn stages(&self) -> Vec<Vec<usize>> {
+        let mut stage_of = vec![0; self.steps.len()];
+        let mut stages: Vec<Vec<usize>> = vec![];
+        for step in 0..self.steps.len() {
+            let stage = self
+                .predecessors(step)
+                .into_iter()
+                .map(|before| stage_of[before] + 1)
+                .max()
+                .unwrap_or(0);
+            stage_of[step] = stage;
+            if stage == stages.len() {
+                stages.push(vec![]);
+            }
+            stages[stage].push(step);
+        }
+        stages
+    }
+}
+
+// The shared variables which require `earlier` to be performed before `later`, and why
+pub(crate) fn dependencies(earlier: &Action, later: &Action) -> Vec<(String, LinkKind)> {
+    let (reads, writes, assigns) = (later.reads(), later.writes(), later.assigns());
+    let (earlier_reads, earlier_writes) = (earlier.reads(), earlier.writes());
+    let earlier_assigns = earlier.assigns();
+    earlier_writes
+        .union(&earlier_reads)
+        .filter_map(|variable| {
+            let kind = if earlier_writes.contains(variable) && reads.contains(variable) {
+                LinkKind::Causal
+            } else if earlier_reads.contains(variable) && writes.contains(variable) {
+                LinkKind::Threat
+            } else if writes.contains(variable)
+                && (assigns.contains(variable) || earlier_assigns.contains(variable))
+            {
+                LinkKind::Overwrite
+            } else {
+                return None;
+            };
+            Some((variable.to_string(), kind))
+        })
+        .collect()
+}
@@ -0,0 +1,97 @@
+use crate::{Action, State};
+
+/// A contingent plan which branches on the observed outcome of each action.
+#[derive(Debug, Clone)]
+pub enum PlanTree {
+    /// Nothing more is planned.
+    Done { discontentment: f32 },
+    /// Perform an action, then continue with the branch matching its outcome.
+    Step {
+        label: String,
+        action: Box<Action>,
+        discontentment: f32, // Risk-measured final discontentment over the branches
+        time: f32,           // Expected time to the end of the tree
+        cost: f32,           // Expected cost of the actions to the end of the tree
+        branches: Vec<Branch>,
+    },
+}
+
+/// One possible continuation of a `PlanTree` step.
+#[derive(Debug, Clone)]
+pub struct Branch {
+    pub outcome: Option<usize>, // Index into the action's outcomes, or `None` for deterministic actions
+    pub probability: f32,
+    pub state: State, // State expected once the step has completed with this outcome
+    pub next: PlanTree,
+}
+
+impl PlanTree {
+    /// The (risk-measured) discontentment at the end of the tree.
+    pub fn discontentment(&self) -> f32 {
+        match self {
+            Self::Done { discontentment } | Self::Step { discontentment, .. } => *discontentment,
+        }
+    }
+
+    /// The expected time taken to reach the end of the tree.
+    pub fn time(&self) -> f32 {
+        match self {
+            Self::Done { .. } => 0.0,
+            Self::Step { time, .. } => *time,
+        }
+    }
+
+    /// The expected cost of the actions to the end of the tree.
+    pub fn cost(&self) -> f32 {
+        match self {
+            Self::Done { .. } => 0.0,
+            Self::Step { cost, .. } => *cost,
+        }
+    }
+
+    /// The next action to perform, if any.
+    pub fn next_action(&self) -> Option<(&str, &Action)> {
+        match self {
+            Self::Done { .. } => None,
+            Self::Step { label, action, .. } => Some((label, action)),
+        }
+    }
+
+    /// Continue with the branch for the given outcome index of the current step.
+    pub fn follow(&self, outcome: Option<usize>) -> Option<&PlanTree> {
+        self.branches()
+            .iter()
+            .find(|branch| branch.outcome == outcome)
+            .map(|branch| &branch.next)
+    }
+
+    /// Continue with the branch whose expected state matches the observed state.
+    /// Returns `None` if the observation matches no branch, in which case the agent should re-plan.
+    pub fn observe(&self, state: &State) -> Option<&PlanTree> {
+        self.branches()
+            .iter()
+            .find(|branch| &branch.state == state)
+            .map(|branch| &branch.next)
+    }
+
+    /// Number of steps along the longest path through the tree.
+    pub fn depth(&self) -> usize {
+        match self {
+            Self::Done { .. } => 0,
+            Self::Step { branches, .. } => {
+                1 + branches
+                    .iter()
+                    .map(|branch| branch.next.depth())
+                    .max()
+                    .unwrap_or(0)
+            }
+        }
+    }
+
+    fn branches(&self) -> &[Branch] {
+        match self {
+            Self::Done { .. } => &[],
+            Self::Step { branches, .. } => branches,
+        }
+    }
+}
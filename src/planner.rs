@@ -2,29 +2,141 @@ use serde::Deserialize;
 
 use std::{
     cmp::Ordering,
-    collections::{BinaryHeap, HashMap},
+    collections::{BTreeSet, BinaryHeap, HashMap, HashSet},
 };
 
-use crate::{Action, Model, State};
+use crate::{
+    incremental_search::{Dynamics, Edge, Queued, Vertex},
+    mcts::Rng,
+    schedule::{earliest_start, extend_frontier, frontier_dominates, Frontier},
+    team::joint_label,
+    Action, Branch, Constraint, IncrementalSearch, MctsSettings, Model, ModelKey, PartialOrderPlan,
+    PlanTree, RolloutPolicy, Schedule, ScheduledAction, StepFailure, TagFilter, Task,
+    ValidationReport,
+};
 
 #[derive(Debug, Clone, Copy, Deserialize)]
 pub enum Algorithm {
     Traditional,
     Efficient,
     Hybrid,
+    /// Hierarchical task network planning, which decomposes the root tasks into actions.
+    Htn,
+    /// Monte Carlo tree search, which samples action sequences for a fixed number of iterations.
+    Mcts,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize)]
 pub enum Solution {
     Fast,
     Best,
+    /// Exhaustive search which considers every outcome of stochastic actions.
+    Stochastic,
+}
+
+/// How a distribution of final discontentment is reduced to a single score when planning under uncertainty.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub enum RiskMeasure {
+    /// The probability-weighted mean discontentment.
+    #[default]
+    Expected,
+    /// The discontentment of the worst possible outcome.
+    WorstCase,
+    /// The mean discontentment of the worst `alpha` fraction of outcomes (conditional value at risk).
+    CVaR(f32),
+}
+
+impl RiskMeasure {
+    pub fn evaluate(&self, outcomes: &[PlanOutcome]) -> f32 {
+        match self {
+            Self::Expected => outcomes
+                .iter()
+                .map(|outcome| outcome.probability * outcome.discontentment)
+                .sum(),
+            Self::WorstCase => outcomes
+                .iter()
+                .filter(|outcome| outcome.probability > 0.0)
+                .map(|outcome| outcome.discontentment)
+                .fold(0.0, f32::max),
+            Self::CVaR(alpha) => {
+                let mut worst_first = outcomes.to_vec();
+                worst_first.sort_by(|a, b| b.discontentment.total_cmp(&a.discontentment));
+
+                let alpha = alpha.clamp(f32::EPSILON, 1.0);
+                let mut remaining = alpha;
+                let mut total = 0.0;
+                for outcome in worst_first {
+                    let probability = outcome.probability.min(remaining);
+                    total += probability * outcome.discontentment;
+                    remaining -= probability;
+                    if remaining <= 0.0 {
+                        break;
+                    }
+                }
+                total / (alpha - remaining.max(0.0)).max(f32::EPSILON)
+            }
+        }
+    }
+}
+
+/// One possible result of executing a plan with stochastic actions.
+#[derive(Debug, Clone, Copy)]
+pub struct PlanOutcome {
+    pub probability: f32,
+    pub discontentment: f32,
 }
 
 #[derive(Debug, Clone)]
 pub struct Plan {
     pub total_discontentment: f32,
     pub total_time: i32,
+    pub total_cost: f32,
     pub actions: Vec<(String, Action)>,
+    /// Distribution of final discontentment over stochastic action outcomes; empty for deterministic solutions.
+    pub outcomes: Vec<PlanOutcome>,
+}
+
+impl Plan {
+    /// Lift the plan's sequence of actions into a partial order, so that independent actions can run concurrently.
+    pub fn partial_order(&self) -> PartialOrderPlan {
+        PartialOrderPlan::new(self.actions.clone())
+    }
+
+    /// Replay the plan's actions from the given model, reporting the first step which cannot be performed (if any)
+    /// and the discontentment of the resulting model.
+    pub fn validate(&self, model: &Model) -> ValidationReport {
+        let mut model = model.clone();
+        let mut failure = None;
+        for (step, (label, action)) in self.actions.iter().enumerate() {
+            match model.try_apply(label.clone(), action) {
+                Ok(next_model) => model = next_model,
+                Err(error) => {
+                    failure = Some(StepFailure {
+                        step,
+                        label: label.clone(),
+                        error,
+                    });
+                    break;
+                }
+            }
+        }
+        ValidationReport {
+            failure,
+            discontentment: model.calculate_discontentment(),
+            model,
+        }
+    }
+
+    /// Schedule the plan's actions from the given start time, overlapping those which don't depend on each other.
+    pub fn schedule(&self, start_time: i32) -> Schedule {
+        let mut schedule = Schedule::new(start_time);
+        for (label, action) in &self.actions {
+            schedule.push(label.clone(), action.clone());
+        }
+        schedule.total_discontentment = self.total_discontentment;
+        schedule.total_cost = self.total_cost;
+        schedule
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -33,6 +145,16 @@ pub struct Planner {
     solution: Solution,
     max_depth: usize,
     actions: HashMap<String, Action>,
+    risk: RiskMeasure,
+    cost_weight: f32,
+    constraints: Vec<Constraint>,
+    tag_filter: TagFilter,
+    tag_costs: HashMap<String, f32>,
+    tasks: HashMap<String, Task>,
+    root_tasks: Vec<String>,
+    repair_budget: usize,
+    switching_cost: f32,
+    mcts: MctsSettings,
 }
 
 impl Planner {
@@ -48,11 +170,41 @@ impl Planner {
             solution,
             max_depth,
             actions,
+            risk: RiskMeasure::default(),
+            cost_weight: 0.0,
+            constraints: vec![],
+            tag_filter: TagFilter::default(),
+            tag_costs: HashMap::new(),
+            tasks: HashMap::new(),
+            root_tasks: vec![],
+            repair_budget: 1000,
+            switching_cost: 0.0,
+            mcts: MctsSettings::default(),
         }
     }
 
+    /// Set how strongly every objective penalises the total cost of a plan's actions.
+    /// Plans are scored as though each unit of cost were this much extra discontentment.
+    pub fn with_cost_weight(mut self, cost_weight: f32) -> Self {
+        self.cost_weight = cost_weight;
+        self
+    }
+
+    /// Replace the actions available to the planner.
+    pub fn with_actions(mut self, actions: HashMap<String, Action>) -> Self {
+        self.actions = actions;
+        self
+    }
+
+    /// Set the risk measure used to score plans with the `Stochastic` solution.
+    pub fn with_risk(mut self, risk: RiskMeasure) -> Self {
+        self.risk = risk;
+        self
+    }
+
     pub fn plan(&self, model: &Model) -> Plan {
-        match (self.algorithm, self.solution) {
+        let model = &model.begin_plan();
+        let plan = match (self.algorithm, self.solution) {
             (Algorithm::Traditional, Solution::Fast) => self.fast_total_plan(model),
             (Algorithm::Efficient, Solution::Fast) => self.fast_efficiency_plan(model),
             (Algorithm::Hybrid, Solution::Fast) => self.fast_hybrid_plan(model),
@@ -68,7 +220,565 @@ impl Planner {
                 let mut memo = HashMap::new();
                 self.best_hybrid_plan(model, self.max_depth, &mut memo)
             }
+            (Algorithm::Mcts, _) => self.mcts_plan(model),
+            (Algorithm::Htn, solution) => {
+                let mut partial = Plan {
+                    total_discontentment: model.calculate_discontentment(),
+                    total_time: 0,
+                    total_cost: 0.0,
+                    actions: vec![],
+                    outcomes: vec![],
+                };
+                let mut best = None;
+                let exhaustive = !matches!(solution, Solution::Fast);
+                self.decompose(
+                    model,
+                    &self.root_tasks,
+                    &mut partial,
+                    0,
+                    &mut best,
+                    exhaustive,
+                );
+                best.unwrap_or(partial)
+            }
+            (algorithm, Solution::Stochastic) => {
+                let mut memo = HashMap::new();
+                let belief = vec![(1.0, model.clone())];
+                self.best_stochastic_plan(algorithm, &belief, self.max_depth, &mut memo)
+            }
+        };
+        expand_macros(plan)
+    }
+
+    /// Build a contingent plan which branches on the outcome of each action, using memoized expectimax search.
+    /// Every branch is planned independently, and each step's branches are scored with the planner's risk measure.
+    /// Macro actions are expanded into steps for their primitive actions, which assume each one's most likely outcome.
+    pub fn plan_tree(&self, model: &Model) -> PlanTree {
+        let mut memo = HashMap::new();
+        self.best_plan_tree(&model.begin_plan(), self.max_depth, &mut memo)
+    }
+
+    /// Temporal planning: search for the schedule with the shortest makespan (plus any weighted cost) which reaches
+    /// zero discontentment, overlapping actions which don't depend on each other's state variables.
+    /// Each candidate schedule is played out along its own timeline, so rates and events apply once per tick of the
+    /// schedule rather than per action. Schedules ending in the same model are pruned when another frees every state
+    /// variable no later, with no more actions and no greater makespan.
+    /// If no plan within `max_depth` reaches zero discontentment, the schedule ending least discontented is returned.
+    pub fn schedule(&self, model: &Model) -> Schedule {
+        let start_model = model.begin_plan();
+
+        // A schedule is dominated by one ending in the same model which frees every variable no later, with no more
+        // actions and no greater makespan (plus weighted cost)
+        let mut expanded: HashMap<ModelKey, Vec<(Frontier, usize, f32)>> = HashMap::new();
+        let is_dominated = |expanded: &HashMap<ModelKey, Vec<(Frontier, usize, f32)>>,
+                            key: &ModelKey,
+                            node: &ScheduleNode| {
+            expanded.get(key).is_some_and(|known| {
+                known.iter().any(|(frontier, steps, cost_so_far)| {
+                    *steps <= node.steps
+                        && *cost_so_far <= node.cost_so_far
+                        && frontier_dominates(frontier, &node.frontier)
+                })
+            })
+        };
+        let bound_actions: Vec<_> = self
+            .actions
+            .iter()
+            .map(|(label, action)| {
+                let steps = action.primitives(label).into_iter().map(|(_, step)| step);
+                (action.writes(), steps.collect())
+            })
+            .collect();
+        let mut frontier = BinaryHeap::new();
+        frontier.push(ScheduleNode {
+            cost_so_far: 0.0,
+            estimated_total: 0.0,
+            total_cost: 0.0,
+            steps: 0,
+            started: start_model.clone(),
+            model: start_model.clone(),
+            schedule: Schedule::new(start_model.time),
+            frontier: Frontier::new(),
+        });
+
+        // A* on makespan, so the first node without discontentment is the best
+        let mut best: Option<(f32, ScheduleNode)> = None;
+        while let Some(node) = frontier.pop() {
+            let key = self.key(&node.model);
+            if is_dominated(&expanded, &key, &node) {
+                continue;
+            }
+            let discontentment = node.model.calculate_discontentment();
+            if best
+                .as_ref()
+                .is_none_or(|(best_discontentment, best_node)| {
+                    discontentment < *best_discontentment
+                        || (discontentment == *best_discontentment
+                            && node.cost_so_far < best_node.cost_so_far)
+                })
+            {
+                best = Some((discontentment, node.clone()));
+            }
+            if discontentment < f32::EPSILON {
+                break;
+            }
+            if node.steps >= self.max_depth {
+                continue;
+            }
+            let known = expanded.entry(key.clone()).or_default();
+            known.retain(|(frontier, steps, cost_so_far)| {
+                !(node.steps <= *steps
+                    && node.cost_so_far <= *cost_so_far
+                    && frontier_dominates(&node.frontier, frontier))
+            });
+            known.push((node.frontier.clone(), node.steps, node.cost_so_far));
+
+            for (label, action) in &self.actions {
+                if !self.allows_steps(&node.model, label, action) {
+                    continue;
+                }
+                // Schedule each step as `Schedule::push` would, from the frontier rather than every earlier action
+                let mut schedule = node.schedule.clone();
+                let scheduled = schedule.actions.len();
+                let mut next_frontier = node.frontier.clone();
+                for (label, action) in action.primitives(label) {
+                    let start = earliest_start(&next_frontier, schedule.start_time, &action);
+                    let added = ScheduledAction {
+                        label,
+                        action,
+                        start,
+                    };
+                    extend_frontier(&mut next_frontier, &added);
+                    schedule.actions.push(added);
+                }
+
+                // Actions starting after every earlier one carry on from the model as the last of those started
+                let in_order = schedule.actions[scheduled..]
+                    .iter()
+                    .all(|added| added.start >= node.started.time);
+                let started = if in_order {
+                    schedule.try_start(&node.started, scheduled)
+                } else {
+                    schedule.try_start(&start_model, 0)
+                };
+                let Ok(next_model) = started
+                    .clone()
+                    .and_then(|started| schedule.try_finish(started))
+                else {
+                    continue;
+                };
+                let makespan = schedule.makespan();
+                let new_total_cost = node.total_cost + self.action_cost(&node.model, action);
+                let new_g = makespan as f32 + self.cost_weight * new_total_cost;
+                let next_node = ScheduleNode {
+                    cost_so_far: new_g,
+                    estimated_total: new_g
+                        + self.makespan_bound(
+                            &next_model,
+                            &next_frontier,
+                            &schedule,
+                            &bound_actions,
+                        ),
+                    total_cost: new_total_cost,
+                    steps: node.steps + 1,
+                    started: started.expect("the schedule was played out"),
+                    model: next_model,
+                    schedule,
+                    frontier: next_frontier,
+                };
+                if !is_dominated(&expanded, &self.key(&next_node.model), &next_node) {
+                    frontier.push(next_node);
+                }
+            }
+        }
+
+        let (discontentment, node) = best.expect("the start node is always searched");
+        let mut schedule = node.schedule;
+        schedule.total_discontentment = discontentment;
+        schedule.total_cost = node.total_cost;
+        schedule
+    }
+
+    // A lower bound on how much a schedule's makespan grows before it reaches zero discontentment: some action must
+    // still be scheduled, and without rates, events or composite goals it must change a variable of an unmet goal.
+    // Each action is given with the variables it changes and its steps.
+    fn makespan_bound(
+        &self,
+        model: &Model,
+        frontier: &Frontier,
+        schedule: &Schedule,
+        actions: &[(BTreeSet<&str>, Vec<Action>)],
+    ) -> f32 {
+        if model.calculate_discontentment() < f32::EPSILON {
+            return 0.0;
+        }
+        let unmet: Option<Vec<&str>> =
+            (model.rates.is_empty() && model.events.is_empty() && model.composite_goals.is_empty())
+                .then(|| {
+                    model
+                        .goals
+                        .iter()
+                        .filter(|(variable, goal)| {
+                            goal.discontentment(*model.state.get(variable).unwrap_or(&0)) > 0.0
+                        })
+                        .map(|(variable, _)| variable.as_str())
+                        .collect()
+                });
+        let end = schedule.start_time + schedule.makespan();
+        actions
+            .iter()
+            .filter(|(writes, _)| {
+                unmet
+                    .as_ref()
+                    .is_none_or(|unmet| unmet.iter().any(|variable| writes.contains(variable)))
+            })
+            .map(|(_, steps)| {
+                steps
+                    .iter()
+                    .map(|step| earliest_start(frontier, schedule.start_time, step) + step.duration)
+                    .fold(end, i32::max)
+                    - end
+            })
+            .min()
+            .unwrap_or(0) as f32
+    }
+
+    /// Set the iterations, exploration and rollout policy used by the `Mcts` algorithm.
+    pub fn with_mcts(mut self, mcts: MctsSettings) -> Self {
+        self.mcts = mcts;
+        self
+    }
+
+    /// Set how much better a new plan must be for `replan` to switch to it from the current plan, when it starts with a
+    /// different action. The margin is in the units of the algorithm's objective: final discontentment (plus weighted
+    /// cost, and risk-measured with the `Stochastic` solution), or its reduction per tick for `Efficient` planning.
+    pub fn with_switching_cost(mut self, switching_cost: f32) -> Self {
+        self.switching_cost = switching_cost;
+        self
+    }
+
+    /// Plan again from the given model, but keep following the current plan (if it is still valid) unless the new plan
+    /// is better by the switching cost. Plans which start with the same action as the current plan switch freely,
+    /// so the agent doesn't jitter between near-equal alternatives.
+    pub fn replan(&self, current: &Plan, model: &Model) -> Plan {
+        let plan = self.plan(model);
+        // A finished plan is never worth keeping, however little the new plan improves on it
+        if current.actions.is_empty() {
+            return plan;
+        }
+        let model = &model.begin_plan();
+        let Some((current_score, current)) = self.objective(model, &current.actions) else {
+            return plan;
+        };
+        let Some((score, _)) = self.objective(model, &plan.actions) else {
+            return plan;
+        };
+        let shares_next_step = matches!(
+            (plan.actions.first(), current.actions.first()),
+            (Some((label, _)), Some((current_label, _))) if label == current_label
+        );
+        if shares_next_step || score + self.switching_cost < current_score {
+            plan
+        } else {
+            current
+        }
+    }
+
+    // Follow a plan's actions from the model, returning its score under the objective of the planner's algorithm and
+    // solution (lower is better) along with the plan's recomputed totals, or `None` if it can't be followed
+    fn objective(&self, model: &Model, actions: &[(String, Action)]) -> Option<(f32, Plan)> {
+        let is_stochastic = matches!(self.solution, Solution::Stochastic)
+            && matches!(
+                self.algorithm,
+                Algorithm::Traditional | Algorithm::Efficient | Algorithm::Hybrid
+            );
+        let plan = if is_stochastic {
+            let mut belief = vec![(1.0, model.clone())];
+            let mut total_cost = 0.0;
+            for (label, action) in actions {
+                total_cost += belief
+                    .iter()
+                    .map(|(probability, model)| probability * self.action_cost(model, action))
+                    .sum::<f32>();
+                belief = self.advance_belief(&belief, label, action)?;
+            }
+            let outcomes: Vec<_> = belief
+                .iter()
+                .map(|(probability, model)| PlanOutcome {
+                    probability: *probability,
+                    discontentment: model.calculate_discontentment(),
+                })
+                .collect();
+            Plan {
+                total_discontentment: self.risk.evaluate(&outcomes),
+                total_time: actions.iter().map(|(_, action)| action.duration).sum(),
+                total_cost,
+                actions: actions.to_vec(),
+                outcomes,
+            }
+        } else {
+            self.follow(model, actions)?
+        };
+
+        let score = plan.total_discontentment + self.cost_weight * plan.total_cost;
+        let score = match self.algorithm {
+            // Efficient planning maximises the reduction in discontentment per tick
+            Algorithm::Efficient => {
+                let reduction = model.calculate_discontentment() - score;
+                -reduction / plan.total_time.max(1) as f32
+            }
+            _ => score,
+        };
+        Some((score, plan))
+    }
+
+    /// Set how many models plan repair may expand before falling back to planning from scratch.
+    pub fn with_repair_budget(mut self, repair_budget: usize) -> Self {
+        self.repair_budget = repair_budget;
+        self
+    }
+
+    /// Repair a plan which may no longer be valid from the given model, rather than planning from scratch.
+    /// Keeps the steps which can still be performed, and searches for the shortest bridge from them to the rest of
+    /// the plan (possibly skipping some of its steps) which ends at least as contented as the plan expected to.
+    /// Falls back to planning from scratch if no such bridge is found within the repair budget.
+    pub fn repair(&self, plan: &Plan, model: &Model) -> Plan {
+        let model = &model.begin_plan();
+        let expected_score = plan.total_discontentment + self.cost_weight * plan.total_cost;
+
+        let mut prefix_model = model.clone();
+        let mut prefix = vec![];
+        for (label, action) in &plan.actions {
+            match self.expand(&prefix_model, label, action) {
+                Some(next_model) => prefix_model = next_model,
+                None => break,
+            }
+            prefix.push((label.clone(), action.clone()));
+        }
+        let suffix = &plan.actions[prefix.len()..];
+
+        // Breadth-first search over bridges, accepting the best repaired plan among the shortest bridges
+        let mut visited = HashSet::from([self.key(&prefix_model)]);
+        let mut frontier = vec![(prefix_model, vec![])];
+        let mut expansions = 0;
+        while !frontier.is_empty() {
+            let mut best: Option<Plan> = None;
+            for (bridge_model, bridge) in &frontier {
+                // Prefer keeping as much of the rest of the plan as possible, within `max_depth` steps
+                let fewest_skipped =
+                    (prefix.len() + bridge.len() + suffix.len()).saturating_sub(self.max_depth);
+                let Some(rest) = (fewest_skipped..=suffix.len()).find_map(|skip| {
+                    self.follow(bridge_model, &suffix[skip..])
+                        .filter(|rest| {
+                            rest.total_discontentment + self.cost_weight * rest.total_cost
+                                <= expected_score + f32::EPSILON
+                        })
+                        .map(|_| &suffix[skip..])
+                }) else {
+                    continue;
+                };
+                let actions: Vec<_> = prefix.iter().chain(bridge).chain(rest).cloned().collect();
+                let repaired = self
+                    .follow(model, &actions)
+                    .expect("the repaired plan was followed in parts");
+                let score =
+                    |plan: &Plan| plan.total_discontentment + self.cost_weight * plan.total_cost;
+                if best
+                    .as_ref()
+                    .is_none_or(|best| score(&repaired) < score(best))
+                {
+                    best = Some(repaired);
+                }
+            }
+            if let Some(best) = best {
+                return expand_macros(best);
+            }
+
+            let mut next_frontier = vec![];
+            for (bridge_model, bridge) in &frontier {
+                for (label, action) in &self.actions {
+                    expansions += 1;
+                    if expansions > self.repair_budget
+                        || prefix.len() + bridge.len() >= self.max_depth
+                    {
+                        return self.plan(model);
+                    }
+                    if let Some(next_model) = self.expand(bridge_model, label, action) {
+                        if visited.insert(self.key(&next_model)) {
+                            let mut next_bridge = bridge.clone();
+                            next_bridge.push((label.clone(), action.clone()));
+                            next_frontier.push((next_model, next_bridge));
+                        }
+                    }
+                }
+            }
+            frontier = next_frontier;
+        }
+        self.plan(model)
+    }
+
+    // Perform a sequence of actions from the model, returning them as a plan if every step can be performed
+    fn follow(&self, model: &Model, actions: &[(String, Action)]) -> Option<Plan> {
+        let mut model = model.clone();
+        let mut total_cost = 0.0;
+        for (label, action) in actions {
+            total_cost += self.action_cost(&model, action);
+            model = self.expand(&model, label, action)?;
+        }
+        Some(Plan {
+            total_discontentment: model.calculate_discontentment(),
+            total_time: actions.iter().map(|(_, action)| action.duration).sum(),
+            total_cost,
+            actions: actions.to_vec(),
+            outcomes: vec![],
+        })
+    }
+
+    /// Set the ordering constraints which restrict which actions may follow one another.
+    pub fn with_constraints(mut self, constraints: Vec<Constraint>) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Only plan with the actions whose tags pass the filter.
+    pub fn with_tag_filter(mut self, tag_filter: TagFilter) -> Self {
+        self.tag_filter = tag_filter;
+        self
+    }
+
+    /// Scale the cost of actions by a multiplier for each of their tags.
+    pub fn with_tag_costs(mut self, tag_costs: HashMap<String, f32>) -> Self {
+        self.tag_costs = tag_costs;
+        self
+    }
+
+    /// Set the compound tasks used by `Htn` planning, and the root tasks it decomposes in order.
+    pub fn with_tasks(mut self, tasks: HashMap<String, Task>, root_tasks: Vec<String>) -> Self {
+        self.tasks = tasks;
+        self.root_tasks = root_tasks;
+        self
+    }
+
+    // Whether the planner's tag filter and constraints allow the labelled action to be used next
+    fn allows(&self, model: &Model, label: &str, action: &Action) -> bool {
+        self.tag_filter.allows(action)
+            && self
+                .constraints
+                .iter()
+                .all(|constraint| constraint.allows(model, label))
+    }
+
+    // The planner for a team's joint plan: its constraints and tasks are repeated for each agent, over the agent's
+    // own actions labelled `agent/label`, and the root tasks are decomposed for each agent in turn
+    pub(crate) fn for_agents(&self, agents: &[&str], actions: HashMap<String, Action>) -> Self {
+        let mut planner = self.clone().with_actions(actions);
+        planner.constraints = agents
+            .iter()
+            .flat_map(|agent| {
+                self.constraints
+                    .iter()
+                    .map(|constraint| constraint.relabel(|label| joint_label(agent, label)))
+            })
+            .collect();
+        planner.tasks = agents
+            .iter()
+            .flat_map(|agent| {
+                self.tasks.iter().map(|(name, task)| {
+                    let task = task.relabel(|label| joint_label(agent, label));
+                    (joint_label(agent, name), task)
+                })
+            })
+            .collect();
+        planner.root_tasks = agents
+            .iter()
+            .flat_map(|agent| self.root_tasks.iter().map(|task| joint_label(agent, task)))
+            .collect();
+        planner
+    }
+
+    // Whether the planner allows the action (or each step of a macro action) to follow the model's action history
+    fn allows_steps(&self, model: &Model, label: &str, action: &Action) -> bool {
+        if !action.is_macro() {
+            return self.allows(model, label, action);
+        }
+        if !self.tag_filter.allows(action) {
+            return false;
+        }
+        let mut model = model.clone();
+        action.primitives(label).into_iter().all(|(label, step)| {
+            let allowed = self.allows(&model, &label, &step);
+            model.action_history.push((label, step));
+            allowed
+        })
+    }
+
+    // Apply an action, if the planner's constraints allow it (and each step of a macro action)
+    fn expand(&self, model: &Model, label: &str, action: &Action) -> Option<Model> {
+        if !self.tag_filter.allows(action) {
+            None
+        } else if action.is_macro() {
+            action
+                .sequence
+                .iter()
+                .try_fold(model.clone(), |model, (label, step)| {
+                    self.expand(&model, label, step)
+                })
+        } else if self.allows(model, label, action) {
+            model.apply(label.to_string(), action)
+        } else {
+            None
+        }
+    }
+
+    // Apply every outcome of an action, if the planner's constraints allow it
+    fn expand_outcomes(
+        &self,
+        model: &Model,
+        label: &str,
+        action: &Action,
+    ) -> Option<Vec<(f32, Model)>> {
+        if action.is_macro() {
+            self.expand(model, label, action)
+                .map(|model| vec![(1.0, model)])
+        } else if self.allows(model, label, action) {
+            model.apply_outcomes(label.to_string(), action)
+        } else {
+            None
+        }
+    }
+
+    // Key identifying a model during search, including the state of any constraints
+    fn key(&self, model: &Model) -> ModelKey {
+        let mut key = model.key();
+        key.constraints = self
+            .constraints
+            .iter()
+            .map(|constraint| constraint.is_blocking(model))
+            .collect();
+        key
+    }
+
+    // The cost of performing an action from the given model, scaled by its tags' multipliers
+    fn action_cost(&self, model: &Model, action: &Action) -> f32 {
+        if action.is_macro() {
+            let mut model = model.clone();
+            let mut total_cost = 0.0;
+            for (label, step) in &action.sequence {
+                total_cost += self.action_cost(&model, step);
+                match model.apply(label.clone(), step) {
+                    Some(next_model) => model = next_model,
+                    None => break,
+                }
+            }
+            return total_cost;
         }
+        action
+            .tags
+            .iter()
+            .filter_map(|tag| self.tag_costs.get(tag))
+            .fold(action.cost(&model.state), |cost, multiplier| {
+                cost * multiplier
+            })
     }
 
     /// A* fast plan (traditional) focusing on lowering discontentment quickly.
@@ -78,7 +788,7 @@ impl Planner {
             model.calculate_discontentment()
         }
 
-        let mut visited: HashMap<State, f32> = HashMap::new();
+        let mut visited: HashMap<ModelKey, f32> = HashMap::new();
         let mut frontier = BinaryHeap::new();
 
         // Initialize
@@ -88,13 +798,14 @@ impl Planner {
             cost_so_far: start_discontent,
             estimated_total: start_discontent + start_h,
             time: 0,
+            total_cost: 0.0,
             model: start_model.clone(),
             plan: vec![],
         });
 
         // A* loop
         while let Some(node) = frontier.pop() {
-            if let Some(&best_known) = visited.get(&node.model.state) {
+            if let Some(&best_known) = visited.get(&self.key(&node.model)) {
                 if node.cost_so_far > best_known {
                     continue;
                 }
@@ -106,19 +817,24 @@ impl Planner {
                 return Plan {
                     total_discontentment: node.model.calculate_discontentment(),
                     total_time: node.time,
+                    total_cost: node.total_cost,
                     actions: node.plan.clone(),
+                    outcomes: vec![],
                 };
             }
-            visited.insert(node.model.state.clone(), node.cost_so_far);
+            visited.insert(self.key(&node.model), node.cost_so_far);
 
             // Expand actions
             for (label, action) in &self.actions {
-                if let Some(next_model) = node.model.apply(label.clone(), action) {
-                    let new_g = node.cost_so_far + next_model.calculate_discontentment();
+                if let Some(next_model) = self.expand(&node.model, label, action) {
+                    let action_cost = self.action_cost(&node.model, action);
+                    let new_g = node.cost_so_far
+                        + next_model.calculate_discontentment()
+                        + self.cost_weight * action_cost;
                     let new_time = node.time + action.duration;
-                    if !visited.contains_key(&next_model.state)
-                        || new_g < visited[&next_model.state]
-                    {
+                    let new_total_cost = node.total_cost + action_cost;
+                    let next_key = self.key(&next_model);
+                    if !visited.contains_key(&next_key) || new_g < visited[&next_key] {
                         let mut new_plan = node.plan.clone();
                         new_plan.push((label.clone(), action.clone()));
                         let new_h = heuristic(&next_model);
@@ -126,6 +842,7 @@ impl Planner {
                             cost_so_far: new_g,
                             estimated_total: new_g + new_h,
                             time: new_time,
+                            total_cost: new_total_cost,
                             model: next_model,
                             plan: new_plan,
                         });
@@ -137,7 +854,9 @@ impl Planner {
         Plan {
             total_discontentment: start_discontent,
             total_time: 0,
+            total_cost: 0.0,
             actions: vec![],
+            outcomes: vec![],
         }
     }
 
@@ -150,7 +869,7 @@ impl Planner {
             model.calculate_discontentment()
         }
 
-        let mut visited: HashMap<State, f32> = HashMap::new();
+        let mut visited: HashMap<ModelKey, f32> = HashMap::new();
         let mut frontier = BinaryHeap::new();
 
         let start_discontent = start_model.calculate_discontentment();
@@ -159,13 +878,14 @@ impl Planner {
             cost_so_far: 0.0, // We'll accumulate "inefficiency" as cost
             estimated_total: start_h,
             time: 0,
+            total_cost: 0.0,
             model: start_model.clone(),
             plan: vec![],
         });
 
         // A* loop
         while let Some(node) = frontier.pop() {
-            if let Some(&best_known) = visited.get(&node.model.state) {
+            if let Some(&best_known) = visited.get(&self.key(&node.model)) {
                 if node.cost_so_far > best_known {
                     continue;
                 }
@@ -177,24 +897,28 @@ impl Planner {
                 return Plan {
                     total_discontentment: node.model.calculate_discontentment(),
                     total_time: node.time,
+                    total_cost: node.total_cost,
                     actions: node.plan.clone(),
+                    outcomes: vec![],
                 };
             }
-            visited.insert(node.model.state.clone(), node.cost_so_far);
+            visited.insert(self.key(&node.model), node.cost_so_far);
 
             // Expand actions
             for (label, action) in &self.actions {
-                if let Some(next_model) = node.model.apply(label.clone(), action) {
+                if let Some(next_model) = self.expand(&node.model, label, action) {
                     let discontent_delta = node.model.calculate_discontentment()
                         - next_model.calculate_discontentment();
-                    let efficiency = discontent_delta / action.duration.max(1) as f32;
+                    let action_cost = self.action_cost(&node.model, action);
+                    let efficiency = (discontent_delta - self.cost_weight * action_cost)
+                        / action.duration.max(1) as f32;
                     // Accumulate cost as the inverse of efficiency
                     let new_cost = node.cost_so_far + 1.0 / (efficiency + 1e-6);
                     let new_time = node.time + action.duration;
+                    let new_total_cost = node.total_cost + action_cost;
 
-                    if !visited.contains_key(&next_model.state)
-                        || new_cost < visited[&next_model.state]
-                    {
+                    let next_key = self.key(&next_model);
+                    if !visited.contains_key(&next_key) || new_cost < visited[&next_key] {
                         let mut new_plan = node.plan.clone();
                         new_plan.push((label.clone(), action.clone()));
                         let new_h = efficiency_heuristic(&next_model);
@@ -202,6 +926,7 @@ impl Planner {
                             cost_so_far: new_cost,
                             estimated_total: new_cost + new_h,
                             time: new_time,
+                            total_cost: new_total_cost,
                             model: next_model,
                             plan: new_plan,
                         });
@@ -213,7 +938,9 @@ impl Planner {
         Plan {
             total_discontentment: start_discontent,
             total_time: 0,
+            total_cost: 0.0,
             actions: vec![],
+            outcomes: vec![],
         }
     }
 
@@ -223,7 +950,7 @@ impl Planner {
             model.calculate_discontentment()
         }
 
-        let mut visited: HashMap<State, f32> = HashMap::new();
+        let mut visited: HashMap<ModelKey, f32> = HashMap::new();
         let mut frontier = BinaryHeap::new();
 
         let start_discontent = start_model.calculate_discontentment();
@@ -232,13 +959,14 @@ impl Planner {
             cost_so_far: 0.0,
             estimated_total: start_h,
             time: 0,
+            total_cost: 0.0,
             model: start_model.clone(),
             plan: vec![],
         });
 
         // A* loop
         while let Some(node) = frontier.pop() {
-            if let Some(&best_known) = visited.get(&node.model.state) {
+            if let Some(&best_known) = visited.get(&self.key(&node.model)) {
                 if node.cost_so_far > best_known {
                     continue;
                 }
@@ -250,13 +978,15 @@ impl Planner {
                 return Plan {
                     total_discontentment: node.model.calculate_discontentment(),
                     total_time: node.time,
+                    total_cost: node.total_cost,
                     actions: node.plan.clone(),
+                    outcomes: vec![],
                 };
             }
-            visited.insert(node.model.state.clone(), node.cost_so_far);
+            visited.insert(self.key(&node.model), node.cost_so_far);
 
             for (label, action) in &self.actions {
-                if let Some(next_model) = node.model.apply(label.clone(), action) {
+                if let Some(next_model) = self.expand(&node.model, label, action) {
                     let discontent_delta = node.model.calculate_discontentment()
                         - next_model.calculate_discontentment();
                     let efficiency = discontent_delta / action.duration.max(1) as f32;
@@ -269,12 +999,13 @@ impl Planner {
                         next_model.calculate_discontentment()
                     };
 
-                    let new_cost = node.cost_so_far + metric;
+                    let action_cost = self.action_cost(&node.model, action);
+                    let new_cost = node.cost_so_far + metric + self.cost_weight * action_cost;
                     let new_time = node.time + action.duration;
+                    let new_total_cost = node.total_cost + action_cost;
 
-                    if !visited.contains_key(&next_model.state)
-                        || new_cost < visited[&next_model.state]
-                    {
+                    let next_key = self.key(&next_model);
+                    if !visited.contains_key(&next_key) || new_cost < visited[&next_key] {
                         let mut new_plan = node.plan.clone();
                         new_plan.push((label.clone(), action.clone()));
                         let new_h = hybrid_heuristic(&next_model);
@@ -282,6 +1013,7 @@ impl Planner {
                             cost_so_far: new_cost,
                             estimated_total: new_cost + new_h,
                             time: new_time,
+                            total_cost: new_total_cost,
                             model: next_model,
                             plan: new_plan,
                         });
@@ -293,18 +1025,254 @@ impl Planner {
         Plan {
             total_discontentment: start_discontent,
             total_time: 0,
+            total_cost: 0.0,
             actions: vec![],
+            outcomes: vec![],
+        }
+    }
+
+    /// Plan with Lifelong Planning A* (LPA*), keeping the search graph in `search` between calls. When the agent has
+    /// moved to a model the last search reached, or the goals or action costs have changed, only the models whose
+    /// cost from the start has changed are searched again.
+    /// Finds the plan with the least discontentment accumulated over its steps (plus weighted cost) which reaches
+    /// zero discontentment within `max_depth` steps, or failing that the least discontented model reached.
+    /// Only `Traditional` planning with the `Fast` solution searches incrementally; other algorithms plan from scratch.
+    pub fn plan_incremental(&self, model: &Model, search: &mut IncrementalSearch) -> Plan {
+        if !matches!(
+            (self.algorithm, self.solution),
+            (Algorithm::Traditional, Solution::Fast)
+        ) {
+            return self.plan(model);
+        }
+        let model = &model.begin_plan();
+        search.prepare(Dynamics {
+            actions: self
+                .actions
+                .iter()
+                .map(|(label, action)| {
+                    let action = Action {
+                        cost: None,
+                        ..action.clone()
+                    };
+                    (label.clone(), action)
+                })
+                .collect(),
+            constraints: self.constraints.clone(),
+            tag_filter: self.tag_filter.clone(),
+            rates: model.rates.clone(),
+            events: model.events.clone(),
+            max_depth: self.max_depth,
+        });
+
+        // Costs from an unknown start share nothing with the graph
+        let key = self.key(model);
+        if !search.index.contains_key(&key) {
+            search.reset();
+            search.index.insert(key.clone(), 0);
+            let discontentment = model.calculate_discontentment();
+            search
+                .vertices
+                .push(Vertex::new(model.clone(), discontentment));
+        }
+        self.update_edge_costs(model, search);
+        let start = search.index[&key];
+        if start != search.start || search.vertices[start].rhs != 0.0 {
+            let previous = std::mem::replace(&mut search.start, start);
+            search.vertices[start].rhs = 0.0;
+            search.vertices[start].depth = 0;
+            queue_vertex(search, start);
+            if previous != start {
+                self.update_vertex(search, previous);
+            }
+        }
+        update_goal(search);
+        self.compute_costs(model, search);
+
+        // Walk back from the cheapest model without discontentment, or the least discontented model reached
+        let target = if search.goal.g.is_finite() {
+            (0..search.vertices.len())
+                .filter(|&v| search.vertices[v].discontentment < f32::EPSILON)
+                .min_by(|&a, &b| search.vertices[a].g.total_cmp(&search.vertices[b].g))
+        } else {
+            (0..search.vertices.len())
+                .filter(|&v| search.vertices[v].g.is_finite())
+                .min_by(|&a, &b| {
+                    let (a, b) = (&search.vertices[a], &search.vertices[b]);
+                    (a.discontentment, a.g)
+                        .partial_cmp(&(b.discontentment, b.g))
+                        .unwrap_or(Ordering::Equal)
+                })
+        };
+        let mut labels = vec![];
+        let mut vertex = target.unwrap_or(start);
+        while vertex != start && labels.len() < search.vertices.len() {
+            let Some((cost, predecessor, edge)) = best_predecessor(search, vertex) else {
+                break;
+            };
+            if !cost.is_finite() {
+                break;
+            }
+            labels.push(
+                search.vertices[predecessor].edges.as_ref().unwrap()[edge]
+                    .label
+                    .clone(),
+            );
+            vertex = predecessor;
+        }
+        if vertex != start {
+            return self.plan(model);
+        }
+        let actions: Vec<_> = labels
+            .into_iter()
+            .rev()
+            .take(self.max_depth)
+            .map(|label| {
+                let action = self.actions[&label].clone();
+                (label, action)
+            })
+            .collect();
+        match self.follow(model, &actions) {
+            Some(plan) => expand_macros(plan),
+            None => self.plan(model),
+        }
+    }
+
+    // Bring every edge's cost up to date with the model's goals and the current action costs
+    fn update_edge_costs(&self, model: &Model, search: &mut IncrementalSearch) {
+        for vertex in &mut search.vertices {
+            vertex.discontentment = model.discontentment_of(&vertex.model.state);
+        }
+        let mut changed = vec![];
+        for v in 0..search.vertices.len() {
+            let Some(mut edges) = search.vertices[v].edges.take() else {
+                continue;
+            };
+            for edge in &mut edges {
+                let action = &self.actions[&edge.label];
+                let cost = search.vertices[edge.to].discontentment
+                    + self.cost_weight * self.action_cost(&search.vertices[v].model, action);
+                if cost != edge.cost {
+                    edge.cost = cost;
+                    changed.push(edge.to);
+                }
+            }
+            search.vertices[v].edges = Some(edges);
+        }
+        changed.sort_unstable();
+        changed.dedup();
+        for v in changed {
+            self.update_vertex(search, v);
+        }
+    }
+
+    // Recompute a vertex's cost through its best predecessor, queueing it if that differs from its cost
+    fn update_vertex(&self, search: &mut IncrementalSearch, v: usize) {
+        if v != search.start {
+            let best = best_predecessor(search, v);
+            let depth = best.map_or(0, |(_, predecessor, _)| {
+                search.vertices[predecessor].depth + 1
+            });
+            let vertex = &mut search.vertices[v];
+            vertex.rhs = best.map_or(f32::INFINITY, |(cost, _, _)| cost);
+            vertex.depth = depth;
+        }
+        queue_vertex(search, v);
+    }
+
+    // Process queued vertices in order of cost until the goal's cost is known
+    fn compute_costs(&self, model: &Model, search: &mut IncrementalSearch) {
+        while let Some(&top) = search.queue.peek() {
+            if top.key >= search.goal.key() && search.goal.g == search.goal.rhs {
+                break;
+            }
+            search.queue.pop();
+            let Some(v) = top.vertex else {
+                let goal = &mut search.goal;
+                if goal.g == goal.rhs || goal.key() != top.key {
+                    continue;
+                }
+                if goal.g > goal.rhs {
+                    goal.g = goal.rhs;
+                } else {
+                    goal.g = f32::INFINITY;
+                    update_goal(search);
+                }
+                continue;
+            };
+            let vertex = &mut search.vertices[v];
+            if vertex.g == vertex.rhs || vertex.key() != top.key {
+                continue;
+            }
+            if vertex.g > vertex.rhs {
+                vertex.g = vertex.rhs;
+                self.expand_vertex(model, search, v);
+            } else {
+                vertex.g = f32::INFINITY;
+                self.update_vertex(search, v);
+            }
+            let successors: Vec<_> = search.vertices[v]
+                .edges
+                .iter()
+                .flatten()
+                .map(|edge| edge.to)
+                .collect();
+            for successor in successors {
+                self.update_vertex(search, successor);
+            }
+            if search.vertices[v].discontentment < f32::EPSILON {
+                update_goal(search);
+            }
         }
     }
 
+    // Add the edges from a vertex the first time it is reached within `max_depth` steps, unless it needs no more actions
+    fn expand_vertex(&self, model: &Model, search: &mut IncrementalSearch, v: usize) {
+        let vertex = &search.vertices[v];
+        if vertex.edges.is_some()
+            || vertex.depth >= self.max_depth
+            || vertex.discontentment < f32::EPSILON
+        {
+            return;
+        }
+        let from = vertex.model.clone();
+        let mut edges = vec![];
+        for (label, action) in &self.actions {
+            let Some(next_model) = self.expand(&from, label, action) else {
+                continue;
+            };
+            let key = self.key(&next_model);
+            let to = match search.index.get(&key) {
+                Some(&to) => to,
+                None => {
+                    let to = search.vertices.len();
+                    let discontentment = model.discontentment_of(&next_model.state);
+                    search
+                        .vertices
+                        .push(Vertex::new(next_model, discontentment));
+                    search.index.insert(key, to);
+                    to
+                }
+            };
+            let cost = search.vertices[to].discontentment
+                + self.cost_weight * self.action_cost(&from, action);
+            search.vertices[to].predecessors.push((v, edges.len()));
+            edges.push(Edge {
+                label: label.clone(),
+                to,
+                cost,
+            });
+        }
+        search.vertices[v].edges = Some(edges);
+    }
+
     /// Exhaustive best plan (traditional), using memoized search.
     fn best_total_plan(
         &self,
         model: &Model,
         depth: usize,
-        memo: &mut HashMap<(State, usize), Plan>,
+        memo: &mut HashMap<(ModelKey, usize), Plan>,
     ) -> Plan {
-        let key = (model.state.clone(), depth);
+        let key = (self.key(model), depth);
         if let Some(result) = memo.get(&key) {
             return result.clone();
         }
@@ -313,7 +1281,9 @@ impl Planner {
             let res = Plan {
                 total_discontentment: score,
                 total_time: 0,
+                total_cost: 0.0,
                 actions: vec![],
+                outcomes: vec![],
             };
             memo.insert(key, res.clone());
             return res;
@@ -321,20 +1291,25 @@ impl Planner {
 
         let current_score = model.calculate_discontentment();
         let mut best_score = current_score;
+        let mut best_discontent = current_score;
         let mut best_time = 0;
+        let mut best_cost = 0.0;
         let mut best_plan = vec![];
 
         for (label, action) in &self.actions {
-            if let Some(next_model) = model.apply(label.clone(), action) {
+            if let Some(next_model) = self.expand(model, label, action) {
                 let mut sub_plan = self.best_total_plan(&next_model, depth - 1, memo);
+                let total_cost = sub_plan.total_cost + self.action_cost(model, action);
+                let score = sub_plan.total_discontentment + self.cost_weight * total_cost;
 
-                // Prioritize lower discontentment, then shorter time
-                if sub_plan.total_discontentment < best_score
-                    || (sub_plan.total_discontentment == best_score
-                        && sub_plan.total_time + action.duration < best_time)
+                // Prioritize lower discontentment (and cost), then shorter time
+                if score < best_score
+                    || (score == best_score && sub_plan.total_time + action.duration < best_time)
                 {
-                    best_score = sub_plan.total_discontentment;
+                    best_score = score;
+                    best_discontent = sub_plan.total_discontentment;
                     best_time = sub_plan.total_time + action.duration;
+                    best_cost = total_cost;
                     sub_plan.actions.insert(0, (label.clone(), action.clone()));
                     best_plan = sub_plan.actions.clone();
                 }
@@ -342,9 +1317,11 @@ impl Planner {
         }
 
         let res = Plan {
-            total_discontentment: best_score,
+            total_discontentment: best_discontent,
             total_time: best_time,
+            total_cost: best_cost,
             actions: best_plan,
+            outcomes: vec![],
         };
         memo.insert(key, res.clone());
         res
@@ -355,9 +1332,9 @@ impl Planner {
         &self,
         model: &Model,
         depth: usize,
-        memo: &mut HashMap<(State, usize), Plan>,
+        memo: &mut HashMap<(ModelKey, usize), Plan>,
     ) -> Plan {
-        let key = (model.state.clone(), depth);
+        let key = (self.key(model), depth);
         if let Some(result) = memo.get(&key) {
             return result.clone();
         }
@@ -366,7 +1343,9 @@ impl Planner {
             let res = Plan {
                 total_discontentment: score,
                 total_time: 0,
+                total_cost: 0.0,
                 actions: vec![],
+                outcomes: vec![],
             };
             memo.insert(key, res.clone());
             return res;
@@ -376,13 +1355,16 @@ impl Planner {
         let mut best_efficiency = f32::MIN;
         let mut best_time = 0;
         let mut best_discontent = current_score;
+        let mut best_cost = 0.0;
         let mut best_plan = vec![];
 
         for (label, action) in &self.actions {
-            if let Some(next_model) = model.apply(label.clone(), action) {
+            if let Some(next_model) = self.expand(model, label, action) {
                 let sub_plan = self.best_efficiency_plan(&next_model, depth - 1, memo);
 
-                let total_discontent_delta = current_score - sub_plan.total_discontentment;
+                let total_cost = sub_plan.total_cost + self.action_cost(model, action);
+                let total_discontent_delta =
+                    current_score - sub_plan.total_discontentment - self.cost_weight * total_cost;
                 let total_time = sub_plan.total_time + action.duration;
 
                 // Calculate efficiency: discontent delta per unit time
@@ -395,6 +1377,7 @@ impl Planner {
                     best_efficiency = total_efficiency;
                     best_time = total_time;
                     best_discontent = sub_plan.total_discontentment;
+                    best_cost = total_cost;
                     let mut new_plan = sub_plan.actions.clone();
                     new_plan.insert(0, (label.clone(), action.clone()));
                     best_plan = new_plan;
@@ -405,7 +1388,9 @@ impl Planner {
         let res = Plan {
             total_discontentment: best_discontent,
             total_time: best_time,
+            total_cost: best_cost,
             actions: best_plan,
+            outcomes: vec![],
         };
         memo.insert(key, res.clone());
         res
@@ -416,9 +1401,9 @@ impl Planner {
         &self,
         model: &Model,
         depth: usize,
-        memo: &mut HashMap<(State, usize), Plan>,
+        memo: &mut HashMap<(ModelKey, usize), Plan>,
     ) -> Plan {
-        let key = (model.state.clone(), depth);
+        let key = (self.key(model), depth);
         if let Some(result) = memo.get(&key) {
             return result.clone();
         }
@@ -427,7 +1412,9 @@ impl Planner {
             let res = Plan {
                 total_discontentment: score,
                 total_time: 0,
+                total_cost: 0.0,
                 actions: vec![],
+                outcomes: vec![],
             };
             memo.insert(key, res.clone());
             return res;
@@ -436,10 +1423,11 @@ impl Planner {
         let current_score = model.calculate_discontentment();
         let mut best_metric = f32::MAX;
         let mut best_time = 0;
+        let mut best_cost = 0.0;
         let mut best_plan = vec![];
 
         for (label, action) in &self.actions {
-            if let Some(next_model) = model.apply(label.clone(), action) {
+            if let Some(next_model) = self.expand(model, label, action) {
                 let discontent_delta = current_score - next_model.calculate_discontentment();
                 let efficiency = discontent_delta / action.duration.max(1) as f32;
 
@@ -452,6 +1440,8 @@ impl Planner {
                     // Minimizing discontent
                     next_model.calculate_discontentment()
                 };
+                let action_cost = self.action_cost(model, action);
+                let metric = metric + self.cost_weight * action_cost;
 
                 let mut sub_plan = self.best_hybrid_plan(&next_model, depth - 1, memo);
 
@@ -461,6 +1451,7 @@ impl Planner {
                 {
                     best_metric = metric;
                     best_time = sub_plan.total_time + action.duration;
+                    best_cost = sub_plan.total_cost + action_cost;
                     sub_plan.actions.insert(0, (label.clone(), action.clone()));
                     best_plan = sub_plan.actions.clone();
                 }
@@ -475,11 +1466,613 @@ impl Planner {
         let res = Plan {
             total_discontentment: final_discontent,
             total_time: best_time,
+            total_cost: best_cost,
             actions: best_plan,
+            outcomes: vec![],
         };
         memo.insert(key, res.clone());
         res
     }
+
+    /// Exhaustive plan over every outcome of stochastic actions (expectimax), using memoized search.
+    /// Each candidate's final discontentment distribution is scored by the planner's risk measure: traditional and
+    /// hybrid planning minimise that score, while efficient planning maximises its reduction per unit time.
+    fn best_stochastic_plan(
+        &self,
+        algorithm: Algorithm,
+        belief: &[(f32, Model)],
+        depth: usize,
+        memo: &mut HashMap<(BeliefKey, usize), Plan>,
+    ) -> Plan {
+        let key = (
+            belief
+                .iter()
+                .map(|(probability, model)| (self.key(model), probability.to_bits()))
+                .collect(),
+            depth,
+        );
+        if let Some(result) = memo.get(&key) {
+            return result.clone();
+        }
+
+        let outcomes: Vec<_> = belief
+            .iter()
+            .map(|(probability, model)| PlanOutcome {
+                probability: *probability,
+                discontentment: model.calculate_discontentment(),
+            })
+            .collect();
+        let current_score = self.risk.evaluate(&outcomes);
+        let mut best = Plan {
+            total_discontentment: current_score,
+            total_time: 0,
+            total_cost: 0.0,
+            actions: vec![],
+            outcomes,
+        };
+
+        if depth > 0 {
+            let mut best_score = current_score;
+            let mut best_efficiency = f32::MIN;
+            for (label, action) in &self.actions {
+                let Some(next_belief) = self.advance_belief(belief, label, action) else {
+                    continue;
+                };
+                let mut sub_plan =
+                    self.best_stochastic_plan(algorithm, &next_belief, depth - 1, memo);
+                let total_time = sub_plan.total_time + action.duration;
+                let expected_cost: f32 = belief
+                    .iter()
+                    .map(|(probability, model)| probability * self.action_cost(model, action))
+                    .sum();
+                let total_cost = sub_plan.total_cost + expected_cost;
+                let score = sub_plan.total_discontentment + self.cost_weight * total_cost;
+
+                let is_better = match algorithm {
+                    Algorithm::Traditional
+                    | Algorithm::Hybrid
+                    | Algorithm::Htn
+                    | Algorithm::Mcts => {
+                        let is_better = score < best_score
+                            || (score == best_score && total_time < best.total_time);
+                        if is_better {
+                            best_score = score;
+                        }
+                        is_better
+                    }
+                    Algorithm::Efficient => {
+                        let efficiency = (current_score - score) / total_time.max(1) as f32;
+                        let is_better = efficiency > best_efficiency
+                            || (efficiency == best_efficiency && total_time < best.total_time);
+                        if is_better {
+                            best_efficiency = efficiency;
+                        }
+                        is_better
+                    }
+                };
+                if is_better {
+                    sub_plan.actions.insert(0, (label.clone(), action.clone()));
+                    sub_plan.total_time = total_time;
+                    sub_plan.total_cost = total_cost;
+                    best = sub_plan;
+                }
+            }
+        }
+
+        memo.insert(key, best.clone());
+        best
+    }
+
+    /// Hierarchical (HTN) planning: depth-first decomposition of a task list, trying each compound task's applicable
+    /// methods in order and backtracking when a method cannot be completed. Keeps the first complete decomposition,
+    /// or when `exhaustive` the one with the lowest final discontentment (and cost). Returns whether to stop searching.
+    fn decompose(
+        &self,
+        model: &Model,
+        tasks: &[String],
+        partial: &mut Plan,
+        nesting: usize,
+        best: &mut Option<Plan>,
+        exhaustive: bool,
+    ) -> bool {
+        let Some((task, rest)) = tasks.split_first() else {
+            let plan = Plan {
+                total_discontentment: model.calculate_discontentment(),
+                ..partial.clone()
+            };
+            let score =
+                |plan: &Plan| plan.total_discontentment + self.cost_weight * plan.total_cost;
+            if best.as_ref().is_none_or(|best| {
+                score(&plan) < score(best)
+                    || (score(&plan) == score(best) && plan.total_time < best.total_time)
+            }) {
+                *best = Some(plan);
+            }
+            return !exhaustive;
+        };
+
+        if let Some(compound) = self.tasks.get(task) {
+            // Decomposing more tasks than exist without performing an action means a method recurses on itself
+            if nesting > self.tasks.len() {
+                return false;
+            }
+            for method in compound.applicable_methods(&model.state) {
+                let tasks: Vec<_> = method.subtasks.iter().chain(rest).cloned().collect();
+                if self.decompose(model, &tasks, partial, nesting + 1, best, exhaustive) {
+                    return true;
+                }
+            }
+            false
+        } else if let Some(action) = self.actions.get(task) {
+            if partial.actions.len() >= self.max_depth {
+                return false;
+            }
+            let Some(next_model) = self.expand(model, task, action) else {
+                return false;
+            };
+            let (time, cost) = (partial.total_time, partial.total_cost);
+            partial.actions.push((task.clone(), action.clone()));
+            partial.total_time += action.duration;
+            partial.total_cost += self.action_cost(model, action);
+            let done = self.decompose(&next_model, rest, partial, 0, best, exhaustive);
+            partial.actions.pop();
+            (partial.total_time, partial.total_cost) = (time, cost);
+            done
+        } else {
+            false
+        }
+    }
+
+    /// Monte Carlo tree search (UCT): each iteration descends the tree of action sequences by upper confidence bounds,
+    /// adds one untried action, then rolls out to `max_depth` with the rollout policy and scores the final
+    /// discontentment (plus weighted cost). Stochastic outcomes are sampled on every descent.
+    /// Returns whichever is better of the most visited sequence and the best trajectory (tree path plus rollout) seen,
+    /// each cut short at its best prefix.
+    fn mcts_plan(&self, start_model: &Model) -> Plan {
+        let mut actions: Vec<_> = self.actions.iter().collect();
+        actions.sort_by_key(|(label, _)| *label);
+        let mut rng = Rng::new(self.mcts.seed);
+        let start_score = start_model.calculate_discontentment();
+        let mut nodes = vec![MctsNode::new(None, actions.len())];
+        let mut best_trajectory: (f32, Vec<usize>) = (start_score, vec![]);
+
+        for _ in 0..self.mcts.iterations {
+            let mut model = start_model.clone();
+            let mut total_cost = 0.0;
+            let mut path = vec![0];
+            let mut trajectory = vec![];
+            let mut best_prefix = (start_score, 0); // The best score along the trajectory, and where it was reached
+
+            // Selection and expansion
+            let mut node = 0;
+            while path.len() <= self.max_depth && model.calculate_discontentment() >= f32::EPSILON {
+                let (index, child) = if !nodes[node].untried.is_empty() {
+                    let untried = &mut nodes[node].untried;
+                    (untried.swap_remove(rng.below(untried.len())), None)
+                } else if let Some(child) = self.uct_child(&nodes, node) {
+                    (
+                        nodes[child].action.expect("only the root has no action"),
+                        Some(child),
+                    )
+                } else {
+                    break;
+                };
+                let (label, action) = actions[index];
+                let Some(next_model) = self.sample(&model, label, action, &mut rng) else {
+                    // An untried action which can't be performed is dropped, while an existing child may only be
+                    // impossible with the outcomes sampled this time
+                    if child.is_some() {
+                        break;
+                    }
+                    continue;
+                };
+                total_cost += self.action_cost(&model, action);
+                model = next_model;
+                trajectory.push(index);
+                let score = model.calculate_discontentment() + self.cost_weight * total_cost;
+                if score < best_prefix.0 {
+                    best_prefix = (score, trajectory.len());
+                }
+                let child = child.unwrap_or_else(|| {
+                    let child = nodes.len();
+                    nodes.push(MctsNode::new(Some(index), actions.len()));
+                    nodes[node].children.push(child);
+                    child
+                });
+                path.push(child);
+                if nodes[child].visits == 0 {
+                    break;
+                }
+                node = child;
+            }
+
+            // Rollout
+            for _ in path.len()..=self.max_depth {
+                if model.calculate_discontentment() < f32::EPSILON {
+                    break;
+                }
+                let Some((index, next_model)) = self.rollout_step(&model, &actions, &mut rng)
+                else {
+                    break;
+                };
+                total_cost += self.action_cost(&model, actions[index].1);
+                model = next_model;
+                trajectory.push(index);
+                let score = model.calculate_discontentment() + self.cost_weight * total_cost;
+                if score < best_prefix.0 {
+                    best_prefix = (score, trajectory.len());
+                }
+            }
+            if best_prefix.0 < best_trajectory.0 {
+                trajectory.truncate(best_prefix.1);
+                best_trajectory = (best_prefix.0, trajectory);
+            }
+
+            // Backpropagate the improvement over the start, relative to the start's discontentment
+            let score = model.calculate_discontentment() + self.cost_weight * total_cost;
+            let reward = (start_score - score) / start_score.max(1.0);
+            for &node in &path {
+                nodes[node].visits += 1;
+                nodes[node].total_reward += reward;
+            }
+        }
+
+        let mut most_visited = vec![];
+        let mut node = 0;
+        while let Some(&child) = nodes[node]
+            .children
+            .iter()
+            .max_by_key(|&&child| nodes[child].visits)
+        {
+            most_visited.push(nodes[child].action.expect("only the root has no action"));
+            node = child;
+        }
+
+        // Sampled outcomes may have flattered the trajectory, so both candidates are rescored with likely outcomes
+        let score = |plan: &Plan| plan.total_discontentment + self.cost_weight * plan.total_cost;
+        let mut best = self
+            .follow(start_model, &[])
+            .expect("an empty plan can always be followed");
+        for sequence in [most_visited, best_trajectory.1] {
+            let sequence: Vec<_> = sequence
+                .into_iter()
+                .map(|index| (actions[index].0.clone(), actions[index].1.clone()))
+                .collect();
+            for length in 1..=sequence.len() {
+                let Some(plan) = self.follow(start_model, &sequence[..length]) else {
+                    break;
+                };
+                if score(&plan) < score(&best) {
+                    best = plan;
+                }
+            }
+        }
+        best
+    }
+
+    // The child of a node with the highest upper confidence bound
+    fn uct_child(&self, nodes: &[MctsNode], node: usize) -> Option<usize> {
+        let parent_visits = nodes[node].visits.max(1) as f32;
+        let bound = |child: usize| {
+            let visits = nodes[child].visits.max(1) as f32;
+            nodes[child].total_reward / visits
+                + self.mcts.exploration * (parent_visits.ln() / visits).sqrt()
+        };
+        nodes[node]
+            .children
+            .iter()
+            .copied()
+            .max_by(|&a, &b| bound(a).total_cmp(&bound(b)))
+    }
+
+    // Apply an action with an outcome sampled by probability, if the planner allows it
+    fn sample(&self, model: &Model, label: &str, action: &Action, rng: &mut Rng) -> Option<Model> {
+        let mut outcomes = self.expand_outcomes(model, label, action)?;
+        let mut remaining = rng.next_f32();
+        while outcomes.len() > 1 {
+            let (probability, next_model) = outcomes.remove(0);
+            remaining -= probability;
+            if remaining < 0.0 {
+                return Some(next_model);
+            }
+        }
+        outcomes.pop().map(|(_, next_model)| next_model)
+    }
+
+    // Choose and apply an action during a rollout, according to the rollout policy, returning its index
+    fn rollout_step(
+        &self,
+        model: &Model,
+        actions: &[(&String, &Action)],
+        rng: &mut Rng,
+    ) -> Option<(usize, Model)> {
+        let greedy = match self.mcts.rollout {
+            RolloutPolicy::Random => false,
+            RolloutPolicy::Greedy => true,
+            RolloutPolicy::EpsilonGreedy(epsilon) => rng.next_f32() >= epsilon,
+        };
+        if greedy {
+            let mut best: Option<(f32, usize, Model)> = None;
+            for (index, &(label, action)) in actions.iter().enumerate() {
+                if let Some(next_model) = self.sample(model, label, action, rng) {
+                    let score = next_model.calculate_discontentment()
+                        + self.cost_weight * self.action_cost(model, action);
+                    if best
+                        .as_ref()
+                        .is_none_or(|(best_score, _, _)| score < *best_score)
+                    {
+                        best = Some((score, index, next_model));
+                    }
+                }
+            }
+            best.map(|(_, index, next_model)| (index, next_model))
+        } else {
+            // Try the actions in a random order until one can be performed
+            let mut untried: Vec<_> = (0..actions.len()).collect();
+            while !untried.is_empty() {
+                let index = untried.swap_remove(rng.below(untried.len()));
+                let (label, action) = actions[index];
+                if let Some(next_model) = self.sample(model, label, action, rng) {
+                    return Some((index, next_model));
+                }
+            }
+            None
+        }
+    }
+
+    fn best_plan_tree(
+        &self,
+        model: &Model,
+        depth: usize,
+        memo: &mut HashMap<(ModelKey, usize), PlanTree>,
+    ) -> PlanTree {
+        let key = (self.key(model), depth);
+        if let Some(result) = memo.get(&key) {
+            return result.clone();
+        }
+
+        let mut best = PlanTree::Done {
+            discontentment: model.calculate_discontentment(),
+        };
+        if depth > 0 {
+            for (label, action) in &self.actions {
+                let Some(outcomes) = self.expand_outcomes(model, label, action) else {
+                    continue;
+                };
+                let branches: Vec<_> = outcomes
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, (probability, next_model))| Branch {
+                        outcome: (!action.outcomes.is_empty()).then_some(index),
+                        probability,
+                        next: self.best_plan_tree(&next_model, depth - 1, memo),
+                        state: next_model.state,
+                    })
+                    .collect();
+
+                let distribution: Vec<_> = branches
+                    .iter()
+                    .map(|branch| PlanOutcome {
+                        probability: branch.probability,
+                        discontentment: branch.next.discontentment(),
+                    })
+                    .collect();
+                let discontentment = self.risk.evaluate(&distribution);
+                let time = action.duration as f32
+                    + branches
+                        .iter()
+                        .map(|branch| branch.probability * branch.next.time())
+                        .sum::<f32>();
+                let cost = self.action_cost(model, action)
+                    + branches
+                        .iter()
+                        .map(|branch| branch.probability * branch.next.cost())
+                        .sum::<f32>();
+
+                // Prioritize lower discontentment (and cost), then shorter expected time
+                let score = discontentment + self.cost_weight * cost;
+                let best_score = best.discontentment() + self.cost_weight * best.cost();
+                if score < best_score || (score == best_score && time < best.time()) {
+                    best = if action.is_macro() {
+                        let [branch] =
+                            <[Branch; 1]>::try_from(branches).expect("macros are deterministic");
+                        self.macro_steps(model, label, action, discontentment, branch.next)
+                    } else {
+                        PlanTree::Step {
+                            label: label.clone(),
+                            action: Box::new(action.clone()),
+                            discontentment,
+                            time,
+                            cost,
+                            branches,
+                        }
+                    };
+                }
+            }
+        }
+
+        memo.insert(key, best.clone());
+        best
+    }
+
+    // Expand a macro action into a chain of steps for its primitive actions, each with their most likely outcome,
+    // continuing with the given tree once the macro is complete
+    fn macro_steps(
+        &self,
+        model: &Model,
+        label: &str,
+        action: &Action,
+        discontentment: f32,
+        next: PlanTree,
+    ) -> PlanTree {
+        let primitives = action.primitives(label);
+        let mut models = vec![model.clone()];
+        for (label, step) in &primitives {
+            let next_model = self
+                .expand(models.last().unwrap(), label, step)
+                .expect("the macro's steps were expanded during search");
+            models.push(next_model);
+        }
+
+        let mut tree = next;
+        for ((label, step), models) in primitives.into_iter().zip(models.windows(2)).rev() {
+            let outcome = step.most_likely_outcome().and_then(|likely| {
+                step.outcomes
+                    .iter()
+                    .position(|outcome| std::ptr::eq(outcome, likely))
+            });
+            tree = PlanTree::Step {
+                discontentment,
+                time: step.duration as f32 + tree.time(),
+                cost: self.action_cost(&models[0], &step) + tree.cost(),
+                branches: vec![Branch {
+                    outcome,
+                    probability: 1.0,
+                    state: models[1].state.clone(),
+                    next: tree,
+                }],
+                label,
+                action: Box::new(step),
+            };
+        }
+        tree
+    }
+
+    // Apply an action to every model in a belief, merging models which end up identical.
+    // Returns `None` if the action is not possible in every outcome.
+    fn advance_belief(
+        &self,
+        belief: &[(f32, Model)],
+        label: &str,
+        action: &Action,
+    ) -> Option<Vec<(f32, Model)>> {
+        let mut next_belief: Vec<(f32, Model)> = vec![];
+        for (probability, model) in belief {
+            for (outcome_probability, next_model) in self.expand_outcomes(model, label, action)? {
+                let next_probability = probability * outcome_probability;
+                let next_key = self.key(&next_model);
+                match next_belief
+                    .iter_mut()
+                    .find(|(_, other)| self.key(other) == next_key)
+                {
+                    Some((merged_probability, _)) => *merged_probability += next_probability,
+                    None => next_belief.push((next_probability, next_model)),
+                }
+            }
+        }
+        Some(next_belief)
+    }
+}
+
+// The cost of reaching a vertex through each of its predecessors, with the cheapest predecessor and edge
+fn best_predecessor(search: &IncrementalSearch, v: usize) -> Option<(f32, usize, usize)> {
+    search.vertices[v]
+        .predecessors
+        .iter()
+        .map(|&(predecessor, edge)| {
+            let from = &search.vertices[predecessor];
+            let cost = from.g
+                + from
+                    .edges
+                    .as_ref()
+                    .map_or(f32::INFINITY, |edges| edges[edge].cost);
+            (cost, predecessor, edge)
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+// Queue a vertex if its cost is out of date
+fn queue_vertex(search: &mut IncrementalSearch, v: usize) {
+    let vertex = &search.vertices[v];
+    if vertex.g != vertex.rhs {
+        search.queue.push(Queued {
+            key: vertex.key(),
+            vertex: Some(v),
+        });
+    }
+}
+
+// Recompute the goal's cost through the cheapest vertex without discontentment, queueing it if out of date
+fn update_goal(search: &mut IncrementalSearch) {
+    search.goal.rhs = search
+        .vertices
+        .iter()
+        .filter(|vertex| vertex.discontentment < f32::EPSILON)
+        .map(|vertex| vertex.g)
+        .fold(f32::INFINITY, f32::min);
+    if search.goal.g != search.goal.rhs {
+        search.queue.push(Queued {
+            key: search.goal.key(),
+            vertex: None,
+        });
+    }
+}
+
+// Macro actions are planned as single steps, but performed as their primitive actions
+fn expand_macros(mut plan: Plan) -> Plan {
+    plan.actions = plan
+        .actions
+        .iter()
+        .flat_map(|(label, action)| action.primitives(label))
+        .collect();
+    plan
+}
+
+// A node of the search for a schedule, with the schedule so far and the models as its last action starts and ends
+#[derive(Clone)]
+struct ScheduleNode {
+    cost_so_far: f32,
+    estimated_total: f32,
+    total_cost: f32,
+    steps: usize,
+    started: Model,
+    model: Model,
+    schedule: Schedule,
+    frontier: Frontier,
+}
+impl PartialEq for ScheduleNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimated_total == other.estimated_total
+    }
+}
+impl Eq for ScheduleNode {}
+impl Ord for ScheduleNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Flip ordering to make the smallest f-cost the "greatest" priority in the heap
+        other
+            .estimated_total
+            .partial_cmp(&self.estimated_total)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+impl PartialOrd for ScheduleNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Identifies a belief (a distribution over models) during stochastic search, by model key and probability bits.
+type BeliefKey = Vec<(ModelKey, u32)>;
+
+// A node in the Monte Carlo search tree, identified by the sequence of actions leading to it
+struct MctsNode {
+    action: Option<usize>, // Index of the action leading to this node; `None` for the root
+    children: Vec<usize>,
+    untried: Vec<usize>, // Indices of the actions not yet expanded from this node
+    visits: u32,
+    total_reward: f32,
+}
+
+impl MctsNode {
+    fn new(action: Option<usize>, num_actions: usize) -> Self {
+        Self {
+            action,
+            children: vec![],
+            untried: (0..num_actions).collect(),
+            visits: 0,
+            total_reward: 0.0,
+        }
+    }
 }
 
 // A helper struct to hold search nodes for A*.
@@ -491,6 +2084,8 @@ struct AStarNode {
     estimated_total: f32,
     // Time spent for this path.
     time: i32,
+    // Total cost of the actions on this path.
+    total_cost: f32,
     // The current model (state, etc.).
     model: Model,
     // Actions taken to reach this state.
@@ -518,3 +2113,65 @@ impl PartialOrd for AStarNode {
         Some(self.cmp(other))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcomes(distribution: &[(f32, f32)]) -> Vec<PlanOutcome> {
+        distribution
+            .iter()
+            .map(|&(probability, discontentment)| PlanOutcome {
+                probability,
+                discontentment,
+            })
+            .collect()
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn expected_is_the_weighted_mean() {
+        let outcomes = outcomes(&[(0.5, 10.0), (0.3, 20.0), (0.2, 0.0)]);
+        assert_close(RiskMeasure::Expected.evaluate(&outcomes), 11.0);
+    }
+
+    #[test]
+    fn worst_case_ignores_impossible_outcomes() {
+        let outcomes = outcomes(&[(0.9, 10.0), (0.1, 30.0), (0.0, 100.0)]);
+        assert_close(RiskMeasure::WorstCase.evaluate(&outcomes), 30.0);
+    }
+
+    #[test]
+    fn cvar_averages_the_worst_tail() {
+        let outcomes = outcomes(&[(0.5, 10.0), (0.3, 20.0), (0.2, 40.0)]);
+        assert_close(RiskMeasure::CVaR(0.2).evaluate(&outcomes), 40.0);
+        // The worst 40%: all of the 40 and half of the 20
+        assert_close(RiskMeasure::CVaR(0.4).evaluate(&outcomes), 30.0);
+        assert_close(
+            RiskMeasure::CVaR(1.0).evaluate(&outcomes),
+            RiskMeasure::Expected.evaluate(&outcomes),
+        );
+    }
+
+    #[test]
+    fn cvar_with_alpha_out_of_range() {
+        let outcomes = outcomes(&[(0.5, 10.0), (0.5, 20.0)]);
+        assert_close(RiskMeasure::CVaR(0.0).evaluate(&outcomes), 20.0);
+        assert_close(RiskMeasure::CVaR(2.0).evaluate(&outcomes), 15.0);
+    }
+
+    #[test]
+    fn no_outcomes() {
+        assert_close(RiskMeasure::Expected.evaluate(&[]), 0.0);
+        assert_close(RiskMeasure::WorstCase.evaluate(&[]), 0.0);
+        assert_close(RiskMeasure::CVaR(0.5).evaluate(&[]), 0.0);
+    }
+}
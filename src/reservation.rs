@@ -0,0 +1,100 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{Model, Plan};
+
+/// Amounts of shared variables which an agent's plan intends to consume.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reservation {
+    pub amounts: HashMap<String, i32>,
+    pub expires_at: i32, // The time by which the plan should have finished
+}
+
+/// Shared resources reserved by independently planning agents, so that they don't all plan to consume the same ones.
+#[derive(Clone, Debug, Default)]
+pub struct ReservationTable {
+    shared: BTreeSet<String>,
+    reservations: HashMap<String, Reservation>,
+}
+
+impl ReservationTable {
+    /// Construct a table for reserving the given shared variables.
+    pub fn new(shared: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            shared: shared.into_iter().map(Into::into).collect(),
+            reservations: HashMap::new(),
+        }
+    }
+
+    /// Reserve the amount of each shared variable that an agent's plan consumes from the model's state: the furthest
+    /// the plan draws it down. Replaces any earlier reservation by the agent, releasing what its old plan needed.
+    pub fn reserve(&mut self, agent: impl Into<String>, plan: &Plan, model: &Model) {
+        let mut lowest: HashMap<&str, i32> = HashMap::new();
+        let mut current = model.clone();
+        for (label, action) in &plan.actions {
+            let Some(next) = current.apply(label.clone(), action) else {
+                break;
+            };
+            for variable in &self.shared {
+                let value = *next.state.get(variable).unwrap_or(&0);
+                lowest
+                    .entry(variable)
+                    .and_modify(|lowest| *lowest = (*lowest).min(value))
+                    .or_insert(value);
+            }
+            current = next;
+        }
+
+        let amounts = lowest
+            .into_iter()
+            .map(|(variable, lowest)| {
+                let amount = model.state.get(variable).unwrap_or(&0) - lowest;
+                (variable.to_string(), amount)
+            })
+            .filter(|(_, amount)| *amount > 0)
+            .collect();
+        self.reservations.insert(
+            agent.into(),
+            Reservation {
+                amounts,
+                expires_at: model.time + plan.total_time,
+            },
+        );
+    }
+
+    /// Release an agent's reservation, such as when it abandons its plan.
+    pub fn release(&mut self, agent: &str) -> Option<Reservation> {
+        self.reservations.remove(agent)
+    }
+
+    /// Release every reservation whose plan should have finished before the given time.
+    pub fn expire(&mut self, time: i32) {
+        self.reservations
+            .retain(|_, reservation| reservation.expires_at >= time);
+    }
+
+    pub fn reservation(&self, agent: &str) -> Option<&Reservation> {
+        self.reservations.get(agent)
+    }
+
+    /// The total amount of each shared variable reserved by agents other than the given one.
+    pub fn reserved_by_others(&self, agent: &str) -> HashMap<String, i32> {
+        let mut reserved = HashMap::new();
+        for (_, reservation) in self
+            .reservations
+            .iter()
+            .filter(|(other, _)| *other != agent)
+        {
+            for (variable, amount) in &reservation.amounts {
+                *reserved.entry(variable.clone()).or_insert(0) += amount;
+            }
+        }
+        reserved
+    }
+
+    /// The model as the given agent should plan from it, with only the unreserved quantity of each shared variable.
+    pub fn visible_to(&self, agent: &str, model: &Model) -> Model {
+        let mut visible = model.clone();
+        visible.state = model.state.withhold(&self.reserved_by_others(agent));
+        visible
+    }
+}
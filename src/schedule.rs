@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+use crate::{partial_order::dependencies, Action, ApplyError, Model};
+
+// The latest end of the actions writing, reading and assigning each variable
+pub(crate) type Frontier = BTreeMap<String, (i32, i32, i32)>;
+
+/// An action scheduled to start at a given time.
+#[derive(Clone, Debug)]
+pub struct ScheduledAction {
+    pub label: String,
+    pub action: Action,
+    pub start: i32,
+}
+
+impl ScheduledAction {
+    pub fn end(&self) -> i32 {
+        self.start + self.action.duration
+    }
+}
+
+/// Actions with start times, where actions which don't depend on each other's state variables overlap.
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    pub total_discontentment: f32,
+    pub total_cost: f32,
+    pub start_time: i32,
+    pub actions: Vec<ScheduledAction>,
+}
+
+impl Schedule {
+    /// An empty schedule beginning at the given time.
+    pub fn new(start_time: i32) -> Self {
+        Self {
+            total_discontentment: 0.0,
+            total_cost: 0.0,
+            start_time,
+            actions: vec![],
+        }
+    }
+
+    /// Schedule an action (or each step of a macro action) to start as soon as every earlier action it depends on has ended.
+    pub fn push(&mut self, label: String, action: Action) {
+        for (label, action) in action.primitives(&label) {
+            let start = self
+                .actions
+                .iter()
+                .filter(|earlier| !dependencies(&earlier.action, &action).is_empty())
+                .map(ScheduledAction::end)
+                .fold(self.start_time, i32::max);
+            self.actions.push(ScheduledAction {
+                label,
+                action,
+                start,
+            });
+        }
+    }
+
+    /// The time from the start of the schedule until every action has ended.
+    pub fn makespan(&self) -> i32 {
+        self.actions
+            .iter()
+            .map(ScheduledAction::end)
+            .fold(self.start_time, i32::max)
+            - self.start_time
+    }
+
+    /// Play the schedule out from the model, applying each action's most likely outcome as it starts and the passive
+    /// rates and events as the schedule's time passes, returning the model once every action has ended.
+    pub fn try_apply(&self, model: &Model) -> Result<Model, ApplyError> {
+        self.try_finish(self.try_start(model, 0)?)
+    }
+
+    // Play out the actions from the given index on, which start no earlier than the model's time, returning the model
+    // as the last of them starts
+    pub(crate) fn try_start(&self, model: &Model, from: usize) -> Result<Model, ApplyError> {
+        let mut actions: Vec<_> = self.actions[from..].iter().collect();
+        actions.sort_by_key(|scheduled| scheduled.start);
+        let mut model = model.clone();
+        for scheduled in actions {
+            model.state = model.pass_time(model.state.clone(), model.time, scheduled.start)?;
+            model.time = scheduled.start;
+            if !model.is_available(&scheduled.label, &scheduled.action) {
+                return Err(ApplyError::Unavailable);
+            }
+            let action = &scheduled.action;
+            model.state = model
+                .state
+                .try_apply_outcome(action, action.most_likely_outcome())?;
+            model
+                .action_history
+                .push((scheduled.label.clone(), action.clone()));
+        }
+        Ok(model)
+    }
+
+    // Pass the time from when the last action started until every action has ended
+    pub(crate) fn try_finish(&self, mut model: Model) -> Result<Model, ApplyError> {
+        let end = self.start_time + self.makespan();
+        model.state = model.pass_time(model.state.clone(), model.time, end)?;
+        model.time = end;
+        Ok(model)
+    }
+
+    /// The actions in progress at the given time.
+    pub fn running_at(&self, time: i32) -> impl Iterator<Item = &ScheduledAction> {
+        self.actions
+            .iter()
+            .filter(move |scheduled| scheduled.start <= time && time < scheduled.end())
+    }
+}
+
+// Account for a newly scheduled action in the frontier
+pub(crate) fn extend_frontier(frontier: &mut Frontier, scheduled: &ScheduledAction) {
+    let end = scheduled.end();
+    let action = &scheduled.action;
+    for variable in action.writes() {
+        let ends = frontier.entry(variable.to_string()).or_default();
+        ends.0 = ends.0.max(end);
+    }
+    for variable in action.reads() {
+        let ends = frontier.entry(variable.to_string()).or_default();
+        ends.1 = ends.1.max(end);
+    }
+    for variable in action.assigns() {
+        let ends = frontier.entry(variable.to_string()).or_default();
+        ends.2 = ends.2.max(end);
+    }
+}
+
+// When a primitive action could start after the actions in the frontier, as `Schedule::push` would schedule it
+pub(crate) fn earliest_start(frontier: &Frontier, start_time: i32, action: &Action) -> i32 {
+    let ends = |variable: &str| frontier.get(variable).copied().unwrap_or_default();
+    let (writes, assigns) = (action.writes(), action.assigns());
+    let after_writers = action.reads().into_iter().map(|variable| ends(variable).0);
+    let after_others = writes.iter().map(|&variable| {
+        let (written, read, assigned) = ends(variable);
+        if assigns.contains(variable) {
+            written.max(read)
+        } else {
+            read.max(assigned)
+        }
+    });
+    after_writers.chain(after_others).fold(start_time, i32::max)
+}
+
+// Whether every variable is free for later actions at least as early in one frontier as in another
+pub(crate) fn frontier_dominates(frontier: &Frontier, other: &Frontier) -> bool {
+    frontier.iter().all(|(variable, ends)| {
+        other.get(variable).is_some_and(|other_ends| {
+            ends.0 <= other_ends.0 && ends.1 <= other_ends.1 && ends.2 <= other_ends.2
+        })
+    })
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+
+use crate::Action;
+
+/// A parameterised action, such as `move_to(location)`, which is grounded into one concrete action per
+/// combination of parameter values.
+/// The remaining fields describe an `Action`, in which `{parameter}` placeholders are replaced by the bound values.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ActionSchema {
+    pub parameters: Vec<Parameter>,
+    #[serde(flatten)]
+    pub action: Mapping,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Parameter {
+    pub name: String,
+    pub domain: Vec<Argument>,
+}
+
+/// A value which may be bound to a parameter.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Argument {
+    Integer(i64),
+    Name(String),
+}
+
+impl fmt::Display for Argument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Integer(value) => write!(f, "{}", value),
+            Self::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl ActionSchema {
+    /// Ground the schema into concrete actions, labelled with their bound arguments (e.g. `buy(apple, 5)`).
+    pub fn ground(&self, name: &str) -> Result<Vec<(String, Action)>, serde_yaml::Error> {
+        let mut bindings: Vec<Vec<(&str, &Argument)>> = vec![vec![]];
+        for parameter in &self.parameters {
+            bindings = bindings
+                .into_iter()
+                .flat_map(|binding| {
+                    parameter.domain.iter().map(move |argument| {
+                        let mut binding = binding.clone();
+                        binding.push((parameter.name.as_str(), argument));
+                        binding
+                    })
+                })
+                .collect();
+        }
+
+        bindings
+            .into_iter()
+            .map(|binding| {
+                let arguments: Vec<_> = binding
+                    .iter()
+                    .map(|(_, argument)| argument.to_string())
+                    .collect();
+                let label = format!("{}({})", name, arguments.join(", "));
+                let action = Value::Mapping(self.action.clone());
+                serde_yaml::from_value(substitute(action, &binding)).map(|action| (label, action))
+            })
+            .collect()
+    }
+}
+
+// Replace `{parameter}` placeholders throughout a YAML value; strings which become integers are converted to numbers
+fn substitute(value: Value, binding: &[(&str, &Argument)]) -> Value {
+    let replace = |text: &str| {
+        binding
+            .iter()
+            .fold(text.to_string(), |text, (name, argument)| {
+                text.replace(&format!("{{{}}}", name), &argument.to_string())
+            })
+    };
+
+    match value {
+        Value::String(text) => {
+            let text = replace(&text);
+            match text.parse::<i64>() {
+                Ok(number) => Value::Number(number.into()),
+                Err(_) => Value::String(text),
+            }
+        }
+        Value::Sequence(values) => Value::Sequence(
+            values
+                .into_iter()
+                .map(|value| substitute(value, binding))
+                .collect(),
+        ),
+        Value::Mapping(mapping) => Value::Mapping(
+            mapping
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        Value::String(text) => Value::String(replace(&text)),
+                        key => key,
+                    };
+                    (key, substitute(value, binding))
+                })
+                .collect(),
+        ),
+        Value::Tagged(tagged) => Value::Tagged(Box::new(serde_yaml::value::TaggedValue {
+            tag: tagged.tag,
+            value: substitute(tagged.value, binding),
+        })),
+        value => value,
+    }
+}
@@ -5,7 +5,7 @@ use std::{
 
 use serde::Deserialize;
 
-use crate::Action;
+use crate::{Action, ApplyError, Event, Expr, Outcome};
 
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
 pub struct State(HashMap<String, i32>);
@@ -23,18 +23,103 @@ impl State {
         self.0.insert(key, value);
     }
 
-    // Try applying an action and return a new State if valid
+    // Withhold the given amounts (such as those reserved by other agents), leaving at least zero of each
+    pub fn withhold(&self, amounts: &HashMap<String, i32>) -> Self {
+        let mut new_props = self.clone();
+        for (key, amount) in amounts {
+            if let Some(value) = new_props.0.get_mut(key) {
+                *value = (*value - amount).max(0);
+            }
+        }
+        new_props
+    }
+
+    // Try applying an action, assuming its most likely outcome, and return a new State if valid
     pub fn apply(&self, action: &Action) -> Option<Self> {
+        self.apply_outcome(action, action.most_likely_outcome())
+    }
+
+    // Try applying an action with the given outcome and return a new State if valid
+    pub fn apply_outcome(&self, action: &Action, outcome: Option<&Outcome>) -> Option<Self> {
+        self.try_apply_outcome(action, outcome).ok()
+    }
+
+    // Try applying an action with the given outcome, returning why it is invalid if it is
+    pub fn try_apply_outcome(
+        &self,
+        action: &Action,
+        outcome: Option<&Outcome>,
+    ) -> Result<Self, ApplyError> {
+        if action.is_macro() {
+            return action
+                .sequence
+                .iter()
+                .try_fold(self.clone(), |state, (_, step)| {
+                    state.try_apply_outcome(step, step.most_likely_outcome())
+                });
+        }
+        let new_props = self.apply_scaled(&action.resolve_deltas(self, outcome), 1)?;
+        if action.effects.is_empty() {
+            return Ok(new_props);
+        }
+        new_props.apply_effects(&action.effects, self)
+    }
+
+    // Set each variable to its effect expression's value in the original state, rounded to the nearest integer
+    fn apply_effects(
+        mut self,
+        effects: &HashMap<String, Expr>,
+        original: &Self,
+    ) -> Result<Self, ApplyError> {
+        for (key, effect) in effects {
+            let new_val = effect.evaluate(original).round() as i32;
+            if new_val < 0 {
+                return Err(ApplyError::Negative {
+                    variable: key.clone(),
+                    value: new_val,
+                });
+            }
+            self.insert(key.clone(), new_val);
+        }
+        Ok(self)
+    }
+
+    // Try applying per-tick rates over the given duration and return a new State if valid
+    pub fn apply_rates(
+        &self,
+        rates: &HashMap<String, i32>,
+        duration: i32,
+    ) -> Result<Self, ApplyError> {
+        self.apply_scaled(rates, duration)
+    }
+
+    // Apply a scheduled event; the world cannot refuse it, so values are clamped at zero
+    pub fn apply_event(&self, event: &Event) -> Self {
+        let mut new_props = self.clone();
+        for (key, delta) in &event.deltas {
+            let old_val = *new_props.get(key).unwrap_or(&0);
+            new_props.insert(key.clone(), (old_val + delta).max(0));
+        }
+        for (key, value) in &event.sets {
+            new_props.insert(key.clone(), (*value).max(0));
+        }
+        new_props
+    }
+
+    fn apply_scaled(&self, deltas: &HashMap<String, i32>, scale: i32) -> Result<Self, ApplyError> {
         let mut new_props = self.clone();
-        for (key, delta) in &action.deltas {
+        for (key, delta) in deltas {
             let old_val = *new_props.get(key).unwrap_or(&0);
-            let new_val = old_val + delta;
+            let new_val = old_val + delta * scale;
             if new_val < 0 {
-                return None;
+                return Err(ApplyError::Negative {
+                    variable: key.clone(),
+                    value: new_val,
+                });
             }
             new_props.insert(key.clone(), new_val);
         }
-        Some(new_props)
+        Ok(new_props)
     }
 }
 
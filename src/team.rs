@@ -0,0 +1,143 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use crate::{Action, Combine, CompositeGoal, Goal, Model, Plan, Planner};
+
+/// One of the agents planned for by a `TeamPlanner`, with its own actions and goals.
+#[derive(Clone, Debug, Default)]
+pub struct TeamMember {
+    pub actions: HashMap<String, Action>,
+    pub goals: HashMap<String, Goal>,
+}
+
+/// A point at which one agent must wait for another agent's step to end before starting its own step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyncPoint {
+    pub agent: String,
+    pub step: usize,
+    pub waits_for: String,
+    pub their_step: usize,
+    pub variable: String, // The shared state variable behind the dependency
+}
+
+#[derive(Clone, Debug)]
+pub struct TeamPlan {
+    /// Every agent's actions interleaved into one sequence, labelled `agent/action`.
+    pub joint: Plan,
+    /// Each agent's own actions, in the order it should perform them.
+    pub plans: HashMap<String, Vec<(String, Action)>>,
+    pub sync_points: Vec<SyncPoint>,
+}
+
+/// Plans jointly for several agents acting on one shared state, so that they cooperate rather than compete.
+#[derive(Clone, Debug)]
+pub struct TeamPlanner {
+    planner: Planner,
+    members: HashMap<String, TeamMember>,
+}
+
+impl TeamPlanner {
+    /// Construct a team planner which searches with the given planner's settings; its own actions are replaced
+    /// by those of the team members, and its constraints and HTN tasks apply to each member's own actions.
+    pub fn new(planner: Planner) -> Self {
+        Self {
+            planner,
+            members: HashMap::new(),
+        }
+    }
+
+    pub fn with_member(mut self, name: impl Into<String>, member: TeamMember) -> Self {
+        self.members.insert(name.into(), member);
+        self
+    }
+
+    /// Plan for every member at once over the model's shared state, minimising their combined discontentment
+    /// (along with that of the model's own goals).
+    /// Since the joint plan never takes a state variable below zero, members never plan to consume the same resource.
+    pub fn plan(&self, model: &Model) -> TeamPlan {
+        let mut model = model.clone();
+        let mut actions = HashMap::new();
+        for (name, member) in &self.members {
+            for (label, action) in &member.actions {
+                actions.insert(joint_label(name, label), relabel(name, action));
+            }
+            let goals = member
+                .goals
+                .iter()
+                .map(|(variable, goal)| CompositeGoal::Leaf {
+                    variable: variable.clone(),
+                    goal: goal.clone(),
+                })
+                .collect();
+            model.composite_goals.insert(
+                joint_label(name, "goals"),
+                CompositeGoal::And {
+                    combine: Combine::Sum,
+                    goals,
+                },
+            );
+        }
+        let mut agents: Vec<_> = self.members.keys().map(String::as_str).collect();
+        agents.sort();
+        let joint = self.planner.for_agents(&agents, actions).plan(&model);
+
+        // Split the joint plan between the members, remembering where each step went
+        let mut plans: HashMap<String, Vec<(String, Action)>> = self
+            .members
+            .keys()
+            .map(|name| (name.clone(), vec![]))
+            .collect();
+        let mut positions = vec![];
+        for (label, action) in &joint.actions {
+            let (name, label) = label
+                .split_once('/')
+                .expect("joint labels name their agent");
+            let plan = plans.entry(name.to_string()).or_default();
+            positions.push((name.to_string(), plan.len()));
+            plan.push((label.to_string(), action.clone()));
+        }
+
+        // Consider each step's latest dependency on each other agent first, skipping those implied by earlier syncs
+        let mut links = joint.partial_order().links;
+        links.sort_by_key(|link| (link.after, Reverse(link.before)));
+        let mut synced: HashMap<(&str, &str), usize> = HashMap::new();
+        let mut sync_points = vec![];
+        for link in links {
+            let (agent, step) = &positions[link.after];
+            let (waits_for, their_step) = &positions[link.before];
+            let pair = (agent.as_str(), waits_for.as_str());
+            if agent == waits_for || synced.get(&pair).is_some_and(|synced| synced >= their_step) {
+                continue;
+            }
+            synced.insert(pair, *their_step);
+            sync_points.push(SyncPoint {
+                agent: agent.clone(),
+                step: *step,
+                waits_for: waits_for.clone(),
+                their_step: *their_step,
+                variable: link.variable,
+            });
+        }
+
+        TeamPlan {
+            joint,
+            plans,
+            sync_points,
+        }
+    }
+}
+
+pub(crate) fn joint_label(agent: &str, label: &str) -> String {
+    format!("{}/{}", agent, label)
+}
+
+// Label the steps of a macro action with their agent, so they can be told apart once the macro is expanded
+fn relabel(agent: &str, action: &Action) -> Action {
+    Action {
+        sequence: action
+            .sequence
+            .iter()
+            .map(|(label, step)| (joint_label(agent, label), relabel(agent, step)))
+            .collect(),
+        ..action.clone()
+    }
+}
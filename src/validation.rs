@@ -0,0 +1,47 @@
+use std::fmt;
+
+use crate::Model;
+
+/// Why an action cannot be applied to a model.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ApplyError {
+    /// The action's cooldown or usage limits don't allow it to be used yet.
+    Unavailable,
+    /// The state variable would go below zero, to the given value.
+    Negative { variable: String, value: i32 },
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unavailable => write!(f, "not available due to its cooldown or usage limits"),
+            Self::Negative { variable, value } => write!(f, "`{}` would be {}", variable, value),
+        }
+    }
+}
+
+impl std::error::Error for ApplyError {}
+
+/// The first step of a plan which cannot be performed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StepFailure {
+    pub step: usize,
+    pub label: String,
+    pub error: ApplyError,
+}
+
+/// The result of replaying a plan's actions from a model.
+#[derive(Clone, Debug)]
+pub struct ValidationReport {
+    /// `None` if every step can be performed.
+    pub failure: Option<StepFailure>,
+    /// The model after every step up to the first failure.
+    pub model: Model,
+    pub discontentment: f32,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.failure.is_none()
+    }
+}